@@ -0,0 +1,362 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Compares the storage cost of past group states in a group with 5000
+//! members to that of the previous layout, which stored the full tree of
+//! every retained epoch in one record and rewrote that record whenever a
+//! message was accepted.
+//!
+//! The group advances through many epochs, each of which adds a member, so
+//! that a past group state is retained for every epoch. Every tenth commit
+//! also updates the committer's path. Run with `cargo bench`.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::Infallible,
+    sync::{
+        RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration as StdDuration, Instant},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use mls_assist::{
+    group::{Group, past_group_states::RetentionPolicy},
+    kv_provider::{KeyValueStore, KvEntries, KvOperation, KvStorage, Namespace},
+    memory_provider::Codec,
+    messages::{AssistedMessageIn, AssistedMessageOut},
+    openmls::prelude::{
+        BasicCredential, Ciphersuite, CredentialWithKey, GroupEpoch, GroupId, KeyPackage, MlsGroup,
+        MlsGroupCreateConfig, MlsMessageBodyIn, MlsMessageIn, MlsMessageOut,
+        PURE_PLAINTEXT_WIRE_FORMAT_POLICY, RatchetTreeIn, SignaturePublicKey,
+    },
+    openmls::treesync::RatchetTree,
+    openmls_rust_crypto::{OpenMlsRustCrypto, RustCrypto},
+    openmls_traits::OpenMlsProvider as _,
+    provider_traits::{MlsAssistProvider, SystemClock},
+    tls_codec::{DeserializeBytes, Serialize as _, Size},
+};
+use openmls_basic_credential::SignatureKeyPair;
+use serde::{Serialize, de::DeserializeOwned};
+
+const MEMBERS: usize = 5000;
+const EPOCHS: usize = 200;
+/// Every this many epochs, the committer also updates its path.
+const PATH_UPDATE_INTERVAL: usize = 10;
+const CIPHERSUITE: Ciphersuite = Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519;
+
+/// Namespaces of past group states and their tree chunks in the key layout
+/// of [`KvStorage`].
+const PAST_GROUP_STATE_NAMESPACES: [Namespace; 2] =
+    [Namespace::PastGroupState, Namespace::PastGroupStateChunk];
+
+/// Returns true if the given key belongs to a past group state record.
+fn is_past_group_state_key(key: &[u8]) -> bool {
+    key.first().is_some_and(|first| {
+        PAST_GROUP_STATE_NAMESPACES
+            .iter()
+            .any(|namespace| *namespace as u8 == *first)
+    })
+}
+
+/// A past group state in the previous layout.
+#[derive(Serialize)]
+struct LegacyPastGroupState {
+    nodes: RatchetTree,
+    creation_time: DateTime<Utc>,
+    potential_joiners: HashSet<SignaturePublicKey>,
+}
+
+/// Tracks the size of the record holding all past group states of a group
+/// in the previous layout. It was encoded with the same codec as the records
+/// of the current layout.
+struct LegacyRecord {
+    key_size: usize,
+    entry_sizes: Vec<usize>,
+}
+
+impl LegacyRecord {
+    fn new(group_id: &GroupId) -> Self {
+        // Keyed like the group's records in the current layout: the
+        // namespace, the length of the encoded group ID and the encoded
+        // group ID.
+        let key_size = 1 + 4 + JsonCodec::to_vec(group_id).unwrap().len();
+        Self {
+            key_size,
+            entry_sizes: Vec::new(),
+        }
+    }
+
+    /// Add the past group state of the given epoch. Only the size of its
+    /// entry in the encoded map of past group states is kept, so that trees
+    /// don't have to be encoded again on every accept.
+    fn add(&mut self, epoch: GroupEpoch, past_group_state: LegacyPastGroupState) {
+        let entry = HashMap::from([(epoch, past_group_state)]);
+        // Without the enclosing braces.
+        self.entry_sizes
+            .push(JsonCodec::to_vec(&entry).unwrap().len() - 2);
+    }
+
+    /// Returns the size of the record, including its key.
+    fn size(&self) -> usize {
+        // The entries are enclosed in braces and separated by commas.
+        let separators = self.entry_sizes.len().saturating_sub(1);
+        self.key_size + 2 + separators + self.entry_sizes.iter().sum::<usize>()
+    }
+}
+
+struct JsonCodec;
+
+impl Codec for JsonCodec {
+    type Error = serde_json::Error;
+
+    fn to_vec<T: Serialize>(payload: &T) -> Result<Vec<u8>, Self::Error> {
+        serde_json::to_vec(payload)
+    }
+
+    fn from_slice<T: DeserializeOwned>(data: &[u8]) -> Result<T, Self::Error> {
+        serde_json::from_slice(data)
+    }
+}
+
+/// An in-memory store that counts the bytes written to past group state
+/// records.
+#[derive(Default)]
+struct CountingStore {
+    entries: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
+    written_bytes: AtomicUsize,
+}
+
+impl CountingStore {
+    fn count_write(&self, key: &[u8], value: &[u8]) {
+        if is_past_group_state_key(key) {
+            self.written_bytes
+                .fetch_add(key.len() + value.len(), Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the bytes written to past group state records since the last
+    /// call.
+    fn take_written_bytes(&self) -> usize {
+        self.written_bytes.swap(0, Ordering::Relaxed)
+    }
+
+    /// Returns the total size of the stored past group state records.
+    fn stored_bytes(&self) -> usize {
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| is_past_group_state_key(key))
+            .map(|(key, value)| key.len() + value.len())
+            .sum()
+    }
+}
+
+impl KeyValueStore for CountingStore {
+    type Error = Infallible;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self.entries.read().unwrap().get(key).cloned())
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), Self::Error> {
+        self.count_write(key, value);
+        self.entries
+            .write()
+            .unwrap()
+            .insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<(), Self::Error> {
+        self.entries.write().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<KvEntries, Self::Error> {
+        Ok(self
+            .entries
+            .read()
+            .unwrap()
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn batch(&self, operations: Vec<KvOperation>) -> Result<(), Self::Error> {
+        for operation in operations {
+            match operation {
+                KvOperation::Put { key, value } => self.put(&key, &value)?,
+                KvOperation::Delete { key } => self.delete(&key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct BenchProvider {
+    crypto: RustCrypto,
+    storage: KvStorage<CountingStore, JsonCodec>,
+    clock: SystemClock,
+}
+
+impl MlsAssistProvider for BenchProvider {
+    type Crypto = RustCrypto;
+
+    type Rand = RustCrypto;
+
+    type Storage = KvStorage<CountingStore, JsonCodec>;
+
+    type Clock = SystemClock;
+
+    fn storage(&self) -> &Self::Storage {
+        &self.storage
+    }
+
+    fn crypto(&self) -> &Self::Crypto {
+        &self.crypto
+    }
+
+    fn rand(&self) -> &Self::Rand {
+        &self.crypto
+    }
+
+    fn clock(&self) -> &Self::Clock {
+        &self.clock
+    }
+}
+
+fn credential_with_key(identity: usize, signer: &SignatureKeyPair) -> CredentialWithKey {
+    CredentialWithKey {
+        credential: BasicCredential::new(identity.to_be_bytes().to_vec()).into(),
+        signature_key: signer.public().into(),
+    }
+}
+
+fn key_package(provider: &OpenMlsRustCrypto, identity: usize) -> KeyPackage {
+    let signer = SignatureKeyPair::new(CIPHERSUITE.signature_algorithm()).unwrap();
+    KeyPackage::builder()
+        .build(
+            CIPHERSUITE,
+            provider,
+            &signer,
+            credential_with_key(identity, &signer),
+        )
+        .unwrap()
+        .key_package()
+        .clone()
+}
+
+fn into_message_body(mls_message: MlsMessageOut) -> MlsMessageBodyIn {
+    let serialized = mls_message.tls_serialize_detached().unwrap();
+    MlsMessageIn::tls_deserialize_exact_bytes(&serialized)
+        .unwrap()
+        .extract()
+}
+
+fn main() {
+    let client_provider = OpenMlsRustCrypto::default();
+    let signer = SignatureKeyPair::new(CIPHERSUITE.signature_algorithm()).unwrap();
+    let mut mls_group = MlsGroup::new(
+        &client_provider,
+        &signer,
+        &MlsGroupCreateConfig::builder()
+            .ciphersuite(CIPHERSUITE)
+            .wire_format_policy(PURE_PLAINTEXT_WIRE_FORMAT_POLICY)
+            .build(),
+        credential_with_key(0, &signer),
+    )
+    .unwrap();
+
+    let key_packages = (1..MEMBERS)
+        .map(|identity| key_package(&client_provider, identity))
+        .collect::<Vec<_>>();
+    mls_group
+        .add_members_without_update(&client_provider, &signer, &key_packages)
+        .unwrap();
+    mls_group.merge_pending_commit(&client_provider).unwrap();
+
+    let provider = BenchProvider {
+        crypto: RustCrypto::default(),
+        storage: KvStorage::new(CountingStore::default()),
+        clock: SystemClock,
+    };
+    let group_info = mls_group
+        .export_group_info(client_provider.crypto(), &signer, false)
+        .unwrap();
+    let MlsMessageBodyIn::GroupInfo(verifiable_group_info) = into_message_body(group_info) else {
+        unreachable!()
+    };
+    let ratchet_tree = RatchetTreeIn::from(mls_group.export_ratchet_tree());
+    let mut group = Group::new(&provider, verifiable_group_info, ratchet_tree).unwrap();
+    let retention_policy = RetentionPolicy::new(Duration::days(30));
+
+    let mut legacy_record = LegacyRecord::new(mls_group.group_id());
+    let mut tree_size = 0;
+    let mut previous_layout_written_bytes = 0;
+    let mut written_bytes = 0;
+    let mut accept_time = StdDuration::ZERO;
+    for epoch in 0..EPOCHS {
+        let key_package = key_package(&client_provider, MEMBERS + epoch);
+        let joiner = key_package.leaf_node().signature_key().clone();
+        let (commit, _, _) = if epoch % PATH_UPDATE_INTERVAL == 0 {
+            mls_group.add_members(&client_provider, &signer, &[key_package])
+        } else {
+            mls_group.add_members_without_update(&client_provider, &signer, &[key_package])
+        }
+        .unwrap();
+        mls_group.merge_pending_commit(&client_provider).unwrap();
+        let group_info = mls_group
+            .export_group_info(client_provider.crypto(), &signer, false)
+            .unwrap();
+        let assisted_message = AssistedMessageOut::new(commit, Some(group_info))
+            .unwrap()
+            .tls_serialize_detached()
+            .unwrap();
+        let assisted_message =
+            AssistedMessageIn::tls_deserialize_exact_bytes(&assisted_message).unwrap();
+
+        let start = Instant::now();
+        let processed_message = group
+            .process_assisted_message(provider.crypto(), assisted_message)
+            .unwrap();
+        group
+            .accept_processed_message(&provider, processed_message, &retention_policy)
+            .unwrap();
+        accept_time += start.elapsed();
+
+        written_bytes += provider.storage().store().take_written_bytes();
+        let tree = group.export_ratchet_tree();
+        tree_size = tree.tls_serialized_len();
+        legacy_record.add(
+            group.epoch(),
+            LegacyPastGroupState {
+                nodes: tree,
+                creation_time: Utc::now(),
+                potential_joiners: HashSet::from([joiner]),
+            },
+        );
+        // The previous layout rewrote the trees of all retained epochs.
+        previous_layout_written_bytes += legacy_record.size();
+    }
+
+    let previous_layout_stored_bytes = legacy_record.size();
+    let stored_bytes = provider.storage().store().stored_bytes();
+    println!("{MEMBERS} members, {EPOCHS} epochs");
+    println!("tree size: {tree_size} bytes");
+    println!("average accept time: {:?}", accept_time / EPOCHS as u32);
+    println!(
+        "stored bytes: {stored_bytes} (previous layout: {previous_layout_stored_bytes}, {:.1}x)",
+        previous_layout_stored_bytes as f64 / stored_bytes as f64
+    );
+    println!(
+        "bytes written per accept: {} (previous layout: {}, {:.1}x)",
+        written_bytes / EPOCHS,
+        previous_layout_written_bytes / EPOCHS,
+        previous_layout_written_bytes as f64 / written_bytes as f64
+    );
+}
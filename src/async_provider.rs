@@ -0,0 +1,856 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Async storage providers.
+//!
+//! Processing messages stays synchronous. Instead, the async versions of the
+//! [`Group`](crate::group::Group) functions load all records of a group from
+//! an [`AsyncMlsAssistStorageProvider`] in one step, run the synchronous
+//! logic against a [`StagingStorage`] and persist the resulting changes in
+//! another step.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    convert::Infallible,
+    future::Future,
+    marker::PhantomData,
+    sync::RwLock,
+};
+
+use openmls_rust_crypto::RustCrypto;
+use openmls_traits::{
+    crypto::OpenMlsCrypto,
+    public_storage::PublicStorageProvider,
+    random::OpenMlsRand,
+    storage::{
+        CURRENT_VERSION,
+        traits::{self, GroupId},
+    },
+};
+use serde::{Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+use crate::{
+    group::errors::StorageError,
+    memory_provider::Codec,
+    provider_traits::{Clock, MlsAssistProvider, MlsAssistStorageProvider, SystemClock},
+};
+
+/// The kinds of records stored for a group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RecordKind {
+    Tree,
+    Context,
+    InterimTranscriptHash,
+    ConfirmationTag,
+    /// Keyed by proposal ref.
+    Proposal,
+    GroupInfo,
+    AcceptedMessages,
+    /// Keyed by epoch.
+    PastGroupState,
+    /// Keyed by chunk hash.
+    PastGroupStateChunk,
+}
+
+/// A record of a group. Keys and values are encoded with the provider's
+/// [`Codec`]. Records of kinds that exist only once per group have the
+/// encoding of `()` as key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub kind: RecordKind,
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+/// A change to the records of a group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordChange {
+    /// Write the given record, replacing any record of the same kind and key.
+    Write(Record),
+    /// Delete the record of the given kind and key.
+    Delete { kind: RecordKind, key: Vec<u8> },
+    /// Delete all records of the given kind.
+    DeleteAll(RecordKind),
+}
+
+/// An async storage provider for MLS-assist. Groups are identified by their
+/// group id encoded with the provider's [`Codec`].
+pub trait AsyncMlsAssistStorageProvider: Sync {
+    type Error: std::error::Error + Send;
+
+    /// Read all records of the given group. Returns an empty vector if the
+    /// group doesn't exist.
+    fn read_group(
+        &self,
+        group_id: &[u8],
+    ) -> impl Future<Output = Result<Vec<Record>, Self::Error>> + Send;
+
+    /// Apply the given changes to the records of the given group in order.
+    /// Either all changes must be applied or none.
+    fn write_group(
+        &self,
+        group_id: &[u8],
+        changes: Vec<RecordChange>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Delete all records of the given group.
+    fn delete_group(&self, group_id: &[u8])
+    -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Returns the ids of all groups for which records are stored.
+    fn group_ids(&self) -> impl Future<Output = Result<Vec<Vec<u8>>, Self::Error>> + Send;
+}
+
+/// An async provider for MLS-assist.
+pub trait AsyncMlsAssistProvider: Sync {
+    type Storage: AsyncMlsAssistStorageProvider;
+    type Codec: Codec;
+    type Crypto: OpenMlsCrypto;
+    type Rand: OpenMlsRand;
+    type Clock: Clock;
+
+    fn storage(&self) -> &Self::Storage;
+
+    fn crypto(&self) -> &Self::Crypto;
+
+    fn rand(&self) -> &Self::Rand;
+
+    fn clock(&self) -> &Self::Clock;
+}
+
+/// Error of the async versions of the [`Group`](crate::group::Group)
+/// functions.
+#[derive(Debug, Error)]
+pub enum AsyncStorageError<S: std::error::Error, C: std::error::Error> {
+    /// Error reading or writing the records of the group.
+    #[error(transparent)]
+    Storage(S),
+    /// Error encoding or decoding a record.
+    #[error(transparent)]
+    Codec(C),
+}
+
+/// The [`AsyncStorageError`] of the given [`AsyncMlsAssistProvider`].
+pub type AsyncProviderError<Provider> = AsyncStorageError<
+    <<Provider as AsyncMlsAssistProvider>::Storage as AsyncMlsAssistStorageProvider>::Error,
+    <<Provider as AsyncMlsAssistProvider>::Codec as Codec>::Error,
+>;
+
+type RecordKey = (Vec<u8>, RecordKind, Vec<u8>);
+
+/// Keys and values of records.
+type RecordEntries = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Result of the helpers of [`StagingStorage`].
+type StagingResult<T, C, E> = Result<T, AsyncStorageError<E, <C as Codec>::Error>>;
+
+/// A synchronous storage that holds the records of groups in memory and
+/// keeps a log of all changes made to them, so that the changes can be
+/// persisted to an [`AsyncMlsAssistStorageProvider`] afterwards.
+pub struct StagingStorage<C: Codec, E: std::error::Error> {
+    records: RwLock<BTreeMap<RecordKey, Vec<u8>>>,
+    changes: RwLock<Vec<(Vec<u8>, RecordChange)>>,
+    _types: PhantomData<(C, E)>,
+}
+
+impl<C: Codec, E: std::error::Error> Default for StagingStorage<C, E> {
+    fn default() -> Self {
+        Self {
+            records: RwLock::default(),
+            changes: RwLock::default(),
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<C: Codec, E: std::error::Error> StagingStorage<C, E> {
+    /// Create a staging storage holding the given records of the given group.
+    pub fn new(group_id: &[u8], records: Vec<Record>) -> Self {
+        let records = records
+            .into_iter()
+            .map(|record| ((group_id.to_vec(), record.kind, record.key), record.value))
+            .collect();
+        Self {
+            records: RwLock::new(records),
+            ..Default::default()
+        }
+    }
+
+    /// Take the changes made to the records of the given group.
+    pub fn take_changes(&self, group_id: &[u8]) -> Vec<RecordChange> {
+        let mut changes = self.changes.write().unwrap();
+        let (group_changes, other_changes) = std::mem::take(&mut *changes)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(change_group_id, _)| change_group_id == group_id);
+        *changes = other_changes;
+        group_changes
+            .into_iter()
+            .map(|(_, change)| change)
+            .collect()
+    }
+
+    fn encode(value: &impl Serialize) -> StagingResult<Vec<u8>, C, E> {
+        C::to_vec(value).map_err(AsyncStorageError::Codec)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> StagingResult<T, C, E> {
+        C::from_slice(bytes).map_err(AsyncStorageError::Codec)
+    }
+
+    fn write(
+        &self,
+        group_id: &impl Serialize,
+        kind: RecordKind,
+        key: &impl Serialize,
+        value: &impl Serialize,
+    ) -> StagingResult<(), C, E> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let key = Self::encode(key)?;
+        let value = Self::encode(value)?;
+        self.records
+            .write()
+            .unwrap()
+            .insert((group_id_bytes.clone(), kind, key.clone()), value.clone());
+        self.changes.write().unwrap().push((
+            group_id_bytes,
+            RecordChange::Write(Record { kind, key, value }),
+        ));
+        Ok(())
+    }
+
+    fn read<T: DeserializeOwned>(
+        &self,
+        group_id: &impl Serialize,
+        kind: RecordKind,
+        key: &impl Serialize,
+    ) -> StagingResult<Option<T>, C, E> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let key = Self::encode(key)?;
+        let records = self.records.read().unwrap();
+        let Some(value) = records.get(&(group_id_bytes, kind, key)) else {
+            return Ok(None);
+        };
+        Self::decode(value).map(Some)
+    }
+
+    fn delete(
+        &self,
+        group_id: &impl Serialize,
+        kind: RecordKind,
+        key: &impl Serialize,
+    ) -> StagingResult<(), C, E> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let key = Self::encode(key)?;
+        self.records
+            .write()
+            .unwrap()
+            .remove(&(group_id_bytes.clone(), kind, key.clone()));
+        self.changes
+            .write()
+            .unwrap()
+            .push((group_id_bytes, RecordChange::Delete { kind, key }));
+        Ok(())
+    }
+
+    fn delete_all(&self, group_id: &impl Serialize, kind: RecordKind) -> StagingResult<(), C, E> {
+        let group_id_bytes = Self::encode(group_id)?;
+        self.records
+            .write()
+            .unwrap()
+            .retain(|(record_group_id, record_kind, _), _| {
+                record_group_id != &group_id_bytes || *record_kind != kind
+            });
+        self.changes
+            .write()
+            .unwrap()
+            .push((group_id_bytes, RecordChange::DeleteAll(kind)));
+        Ok(())
+    }
+
+    /// Returns the keys and values of all records of the given kind.
+    fn read_all(
+        &self,
+        group_id: &impl Serialize,
+        kind: RecordKind,
+    ) -> StagingResult<RecordEntries, C, E> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let records = self.records.read().unwrap();
+        Ok(records
+            .iter()
+            .filter(|((record_group_id, record_kind, _), _)| {
+                record_group_id == &group_id_bytes && *record_kind == kind
+            })
+            .map(|((_, _, key), value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+impl<C: Codec, E: std::error::Error> PublicStorageProvider<CURRENT_VERSION>
+    for StagingStorage<C, E>
+{
+    /// An opaque error returned by all methods on this trait.
+    type PublicError = AsyncStorageError<E, C::Error>;
+
+    /// Write the TreeSync tree.
+    fn write_tree<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        TreeSync: traits::TreeSync<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        tree: &TreeSync,
+    ) -> Result<(), Self::PublicError> {
+        self.write(group_id, RecordKind::Tree, &(), tree)
+    }
+
+    /// Write the interim transcript hash.
+    fn write_interim_transcript_hash<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        InterimTranscriptHash: traits::InterimTranscriptHash<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        interim_transcript_hash: &InterimTranscriptHash,
+    ) -> Result<(), Self::PublicError> {
+        self.write(
+            group_id,
+            RecordKind::InterimTranscriptHash,
+            &(),
+            interim_transcript_hash,
+        )
+    }
+
+    /// Write the group context.
+    fn write_context<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        GroupContext: traits::GroupContext<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        group_context: &GroupContext,
+    ) -> Result<(), Self::PublicError> {
+        self.write(group_id, RecordKind::Context, &(), group_context)
+    }
+
+    /// Write the confirmation tag.
+    fn write_confirmation_tag<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ConfirmationTag: traits::ConfirmationTag<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        confirmation_tag: &ConfirmationTag,
+    ) -> Result<(), Self::PublicError> {
+        self.write(group_id, RecordKind::ConfirmationTag, &(), confirmation_tag)
+    }
+
+    /// Enqueue a proposal.
+    fn queue_proposal<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+        QueuedProposal: traits::QueuedProposal<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+        proposal: &QueuedProposal,
+    ) -> Result<(), Self::PublicError> {
+        self.write(group_id, RecordKind::Proposal, proposal_ref, proposal)
+    }
+
+    /// Returns all queued proposals for the group with group id `group_id`, or an empty vector of none are stored.
+    fn queued_proposals<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+        QueuedProposal: traits::QueuedProposal<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Vec<(ProposalRef, QueuedProposal)>, Self::PublicError> {
+        self.read_all(group_id, RecordKind::Proposal)?
+            .iter()
+            .map(|(proposal_ref_bytes, proposal_bytes)| {
+                Ok((
+                    Self::decode(proposal_ref_bytes)?,
+                    Self::decode(proposal_bytes)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Returns the TreeSync tree for the group with group id `group_id`.
+    fn tree<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        TreeSync: traits::TreeSync<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<TreeSync>, Self::PublicError> {
+        self.read(group_id, RecordKind::Tree, &())
+    }
+
+    /// Returns the group context for the group with group id `group_id`.
+    fn group_context<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        GroupContext: traits::GroupContext<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<GroupContext>, Self::PublicError> {
+        self.read(group_id, RecordKind::Context, &())
+    }
+
+    /// Returns the interim transcript hash for the group with group id `group_id`.
+    fn interim_transcript_hash<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        InterimTranscriptHash: traits::InterimTranscriptHash<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<InterimTranscriptHash>, Self::PublicError> {
+        self.read(group_id, RecordKind::InterimTranscriptHash, &())
+    }
+
+    /// Returns the confirmation tag for the group with group id `group_id`.
+    fn confirmation_tag<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ConfirmationTag: traits::ConfirmationTag<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<ConfirmationTag>, Self::PublicError> {
+        self.read(group_id, RecordKind::ConfirmationTag, &())
+    }
+
+    /// Deletes the tree from storage
+    fn delete_tree<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.delete(group_id, RecordKind::Tree, &())
+    }
+
+    /// Deletes the confirmation tag from storage
+    fn delete_confirmation_tag<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.delete(group_id, RecordKind::ConfirmationTag, &())
+    }
+
+    /// Deletes the group context for the group with given id
+    fn delete_context<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.delete(group_id, RecordKind::Context, &())
+    }
+
+    /// Deletes the interim transcript hash for the group with given id
+    fn delete_interim_transcript_hash<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.delete(group_id, RecordKind::InterimTranscriptHash, &())
+    }
+
+    /// Removes an individual proposal from the proposal queue of the group with the provided id
+    fn remove_proposal<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+    ) -> Result<(), Self::PublicError> {
+        self.delete(group_id, RecordKind::Proposal, proposal_ref)
+    }
+
+    /// Clear the proposal queue for the group with the given id.
+    fn clear_proposal_queue<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.delete_all(group_id, RecordKind::Proposal)
+    }
+}
+
+impl<C: Codec, E: std::error::Error> MlsAssistStorageProvider for StagingStorage<C, E> {
+    /// Changes made by `f` are removed from the records and the change log if
+    /// it returns an error.
+    fn with_transaction<T, F>(
+        &self,
+        f: impl FnOnce() -> Result<T, F>,
+    ) -> Result<Result<T, F>, StorageError<Self>> {
+        let records = self.records.read().unwrap().clone();
+        let change_count = self.changes.read().unwrap().len();
+        let result = f();
+        if result.is_err() {
+            *self.records.write().unwrap() = records;
+            self.changes.write().unwrap().truncate(change_count);
+        }
+        Ok(result)
+    }
+
+    fn group_ids<GroupId: DeserializeOwned>(&self) -> Result<Vec<GroupId>, StorageError<Self>> {
+        let records = self.records.read().unwrap();
+        let group_id_bytes = records
+            .keys()
+            .map(|(group_id_bytes, _, _)| group_id_bytes)
+            .collect::<BTreeSet<_>>();
+        group_id_bytes
+            .into_iter()
+            .map(|group_id_bytes| Self::decode(group_id_bytes))
+            .collect()
+    }
+
+    fn write_past_group_state(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl Serialize,
+        past_group_state: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write(
+            group_id,
+            RecordKind::PastGroupState,
+            epoch,
+            past_group_state,
+        )
+    }
+
+    fn read_past_group_state<PastGroupState: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl Serialize,
+    ) -> Result<Option<PastGroupState>, StorageError<Self>> {
+        self.read(group_id, RecordKind::PastGroupState, epoch)
+    }
+
+    fn delete_past_group_state(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.delete(group_id, RecordKind::PastGroupState, epoch)
+    }
+
+    fn past_group_state_epochs<Epoch: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Vec<Epoch>, StorageError<Self>> {
+        self.read_all(group_id, RecordKind::PastGroupState)?
+            .iter()
+            .map(|(epoch_bytes, _)| Self::decode(epoch_bytes))
+            .collect()
+    }
+
+    fn write_past_group_state_chunk(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl Serialize,
+        chunk: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write(group_id, RecordKind::PastGroupStateChunk, chunk_hash, chunk)
+    }
+
+    fn read_past_group_state_chunk<Chunk: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl Serialize,
+    ) -> Result<Option<Chunk>, StorageError<Self>> {
+        self.read(group_id, RecordKind::PastGroupStateChunk, chunk_hash)
+    }
+
+    fn delete_past_group_state_chunk(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.delete(group_id, RecordKind::PastGroupStateChunk, chunk_hash)
+    }
+
+    fn delete_past_group_states(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), StorageError<Self>> {
+        self.delete_all(group_id, RecordKind::PastGroupState)?;
+        self.delete_all(group_id, RecordKind::PastGroupStateChunk)
+    }
+
+    fn write_accepted_messages(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        accepted_messages: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write(
+            group_id,
+            RecordKind::AcceptedMessages,
+            &(),
+            accepted_messages,
+        )
+    }
+
+    fn read_accepted_messages<AcceptedMessages: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Option<AcceptedMessages>, StorageError<Self>> {
+        self.read(group_id, RecordKind::AcceptedMessages, &())
+    }
+
+    fn delete_accepted_messages(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), StorageError<Self>> {
+        self.delete(group_id, RecordKind::AcceptedMessages, &())
+    }
+
+    fn write_group_info(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        group_info: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write(group_id, RecordKind::GroupInfo, &(), group_info)
+    }
+
+    fn read_group_info<GroupInfo: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Option<GroupInfo>, StorageError<Self>> {
+        self.read(group_id, RecordKind::GroupInfo, &())
+    }
+
+    fn delete_group_info(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), StorageError<Self>> {
+        self.delete(group_id, RecordKind::GroupInfo, &())
+    }
+}
+
+/// A synchronous [`MlsAssistProvider`] that uses a [`StagingStorage`] and the
+/// crypto, randomness and clock of an [`AsyncMlsAssistProvider`].
+pub(crate) struct StagingProvider<'a, Provider: AsyncMlsAssistProvider> {
+    pub(crate) provider: &'a Provider,
+    pub(crate) storage: StagingStorage<Provider::Codec, AsyncStorageErrorOf<Provider>>,
+}
+
+type AsyncStorageErrorOf<Provider> =
+    <<Provider as AsyncMlsAssistProvider>::Storage as AsyncMlsAssistStorageProvider>::Error;
+
+impl<Provider: AsyncMlsAssistProvider> MlsAssistProvider for StagingProvider<'_, Provider> {
+    type Storage = StagingStorage<Provider::Codec, AsyncStorageErrorOf<Provider>>;
+
+    type Crypto = Provider::Crypto;
+
+    type Rand = Provider::Rand;
+
+    type Clock = Provider::Clock;
+
+    fn storage(&self) -> &Self::Storage {
+        &self.storage
+    }
+
+    fn crypto(&self) -> &Self::Crypto {
+        self.provider.crypto()
+    }
+
+    fn rand(&self) -> &Self::Rand {
+        self.provider.rand()
+    }
+
+    fn clock(&self) -> &Self::Clock {
+        self.provider.clock()
+    }
+}
+
+/// Records of a group, keyed by kind and key.
+type GroupRecords = BTreeMap<(RecordKind, Vec<u8>), Vec<u8>>;
+
+/// An [`AsyncMlsAssistStorageProvider`] that keeps all records in memory.
+/// The changes to a group are applied while holding a single lock, so
+/// either all of them are visible or none.
+#[derive(Default)]
+pub struct AsyncMlsAssistMemoryStorage {
+    groups: RwLock<HashMap<Vec<u8>, GroupRecords>>,
+}
+
+impl AsyncMlsAssistStorageProvider for AsyncMlsAssistMemoryStorage {
+    type Error = Infallible;
+
+    async fn read_group(&self, group_id: &[u8]) -> Result<Vec<Record>, Self::Error> {
+        let groups = self.groups.read().unwrap();
+        let Some(records) = groups.get(group_id) else {
+            return Ok(Vec::new());
+        };
+        Ok(records
+            .iter()
+            .map(|((kind, key), value)| Record {
+                kind: *kind,
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect())
+    }
+
+    async fn write_group(
+        &self,
+        group_id: &[u8],
+        changes: Vec<RecordChange>,
+    ) -> Result<(), Self::Error> {
+        let mut groups = self.groups.write().unwrap();
+        let records = groups.entry(group_id.to_vec()).or_default();
+        for change in changes {
+            match change {
+                RecordChange::Write(Record { kind, key, value }) => {
+                    records.insert((kind, key), value);
+                }
+                RecordChange::Delete { kind, key } => {
+                    records.remove(&(kind, key));
+                }
+                RecordChange::DeleteAll(kind) => {
+                    records.retain(|(record_kind, _), _| *record_kind != kind);
+                }
+            }
+        }
+        if records.is_empty() {
+            groups.remove(group_id);
+        }
+        Ok(())
+    }
+
+    async fn delete_group(&self, group_id: &[u8]) -> Result<(), Self::Error> {
+        self.groups.write().unwrap().remove(group_id);
+        Ok(())
+    }
+
+    async fn group_ids(&self) -> Result<Vec<Vec<u8>>, Self::Error> {
+        Ok(self.groups.read().unwrap().keys().cloned().collect())
+    }
+}
+
+/// An [`AsyncMlsAssistProvider`] using [`RustCrypto`] and an
+/// [`AsyncMlsAssistMemoryStorage`]. Values are encoded with the codec `C`.
+pub struct AsyncMlsAssistRustCrypto<C: Codec, K: Clock = SystemClock> {
+    crypto: RustCrypto,
+    storage: AsyncMlsAssistMemoryStorage,
+    clock: K,
+    _codec: PhantomData<C>,
+}
+
+impl<C: Codec, K: Clock> AsyncMlsAssistRustCrypto<C, K> {
+    /// Create a provider on top of the given storage that takes the current
+    /// time from `clock`.
+    pub fn new(storage: AsyncMlsAssistMemoryStorage, clock: K) -> Self {
+        Self {
+            crypto: RustCrypto::default(),
+            storage,
+            clock,
+            _codec: PhantomData,
+        }
+    }
+}
+
+impl<C: Codec, K: Clock + Default> From<AsyncMlsAssistMemoryStorage>
+    for AsyncMlsAssistRustCrypto<C, K>
+{
+    fn from(storage: AsyncMlsAssistMemoryStorage) -> Self {
+        Self::new(storage, K::default())
+    }
+}
+
+impl<C: Codec + Sync, K: Clock + Sync> AsyncMlsAssistProvider for AsyncMlsAssistRustCrypto<C, K> {
+    type Storage = AsyncMlsAssistMemoryStorage;
+
+    type Codec = C;
+
+    type Crypto = RustCrypto;
+
+    type Rand = RustCrypto;
+
+    type Clock = K;
+
+    fn storage(&self) -> &Self::Storage {
+        &self.storage
+    }
+
+    fn crypto(&self) -> &Self::Crypto {
+        &self.crypto
+    }
+
+    fn rand(&self) -> &Self::Rand {
+        &self.crypto
+    }
+
+    fn clock(&self) -> &Self::Clock {
+        &self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::block_on;
+
+    use super::*;
+
+    fn record(kind: RecordKind, key: u8, value: u8) -> Record {
+        Record {
+            kind,
+            key: vec![key],
+            value: vec![value],
+        }
+    }
+
+    #[test]
+    fn changes_are_applied_in_order() {
+        let storage = AsyncMlsAssistMemoryStorage::default();
+        let changes = vec![
+            RecordChange::Write(record(RecordKind::PastGroupState, 1, 1)),
+            RecordChange::Write(record(RecordKind::PastGroupState, 2, 2)),
+            RecordChange::Write(record(RecordKind::GroupInfo, 0, 1)),
+            RecordChange::DeleteAll(RecordKind::PastGroupState),
+            RecordChange::Write(record(RecordKind::PastGroupState, 3, 3)),
+            RecordChange::Write(record(RecordKind::GroupInfo, 0, 2)),
+            RecordChange::Delete {
+                kind: RecordKind::PastGroupState,
+                key: vec![3],
+            },
+        ];
+        block_on(storage.write_group(b"group", changes)).unwrap();
+
+        assert_eq!(
+            block_on(storage.read_group(b"group")).unwrap(),
+            vec![record(RecordKind::GroupInfo, 0, 2)]
+        );
+        assert_eq!(
+            block_on(storage.group_ids()).unwrap(),
+            vec![b"group".to_vec()]
+        );
+        assert!(block_on(storage.read_group(b"other")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn groups_without_records_are_removed() {
+        let storage = AsyncMlsAssistMemoryStorage::default();
+        let write = RecordChange::Write(record(RecordKind::GroupInfo, 0, 1));
+        block_on(storage.write_group(b"deleted", vec![write.clone()])).unwrap();
+        block_on(storage.write_group(b"emptied", vec![write.clone()])).unwrap();
+        block_on(storage.write_group(b"kept", vec![write])).unwrap();
+
+        block_on(storage.delete_group(b"deleted")).unwrap();
+        let delete = RecordChange::Delete {
+            kind: RecordKind::GroupInfo,
+            key: vec![0],
+        };
+        block_on(storage.write_group(b"emptied", vec![delete])).unwrap();
+
+        assert_eq!(
+            block_on(storage.group_ids()).unwrap(),
+            vec![b"kept".to_vec()]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::{BTreeMap, HashSet};
+
+use openmls::prelude::GroupEpoch;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::messages::SerializedMlsMessage;
+
+/// Number of epochs for which accepted messages are retained.
+pub(super) const RETAINED_EPOCHS: u64 = 16;
+
+/// Maximum number of proposals accepted per epoch.
+const MAX_PROPOSALS_PER_EPOCH: usize = 1024;
+
+type MessageHash = [u8; 32];
+
+fn message_hash(message: &SerializedMlsMessage) -> MessageHash {
+    Sha256::digest(&message.0).into()
+}
+
+/// Messages that were accepted for the most recent epochs of a group.
+#[derive(Serialize, Deserialize, Default)]
+pub(super) struct AcceptedMessages {
+    /// The commits that advanced the group from the epoch they're indexed by
+    /// to the next one.
+    commits: BTreeMap<u64, SerializedMlsMessage>,
+    /// Hashes of the commits and proposals accepted in the epoch they're
+    /// indexed by.
+    #[serde(default)]
+    message_hashes: BTreeMap<u64, HashSet<MessageHash>>,
+}
+
+impl AcceptedMessages {
+    /// Record `commit` as the commit that advanced the group from `epoch` to
+    /// the next epoch and drop messages of epochs that are no longer retained.
+    pub(super) fn add_commit(&mut self, epoch: GroupEpoch, commit: SerializedMlsMessage) {
+        self.add_message_hash(epoch, &commit);
+        self.commits.insert(epoch.as_u64(), commit);
+        self.remove_old_epochs(epoch);
+    }
+
+    /// Record `proposal` as accepted in `epoch` and drop messages of epochs
+    /// that are no longer retained. Proposals beyond
+    /// [`MAX_PROPOSALS_PER_EPOCH`] are not recorded.
+    pub(super) fn add_proposal(&mut self, epoch: GroupEpoch, proposal: &SerializedMlsMessage) {
+        if !self.proposal_limit_reached(epoch) {
+            self.add_message_hash(epoch, proposal);
+        }
+        self.remove_old_epochs(epoch);
+    }
+
+    /// Returns true if no further proposals can be accepted in `epoch`.
+    pub(super) fn proposal_limit_reached(&self, epoch: GroupEpoch) -> bool {
+        self.message_hashes
+            .get(&epoch.as_u64())
+            .is_some_and(|message_hashes| message_hashes.len() >= MAX_PROPOSALS_PER_EPOCH)
+    }
+
+    /// Returns the commit that advanced the group from the given epoch to the
+    /// next one, if it is still retained.
+    pub(super) fn commit(&self, epoch: GroupEpoch) -> Option<&SerializedMlsMessage> {
+        self.commits.get(&epoch.as_u64())
+    }
+
+    /// Returns the epoch in which the given message was accepted if it was
+    /// accepted in one of the retained epochs.
+    pub(super) fn accepted_epoch(&self, message: &SerializedMlsMessage) -> Option<GroupEpoch> {
+        let message_hash = message_hash(message);
+        self.message_hashes
+            .iter()
+            .find(|(_, message_hashes)| message_hashes.contains(&message_hash))
+            .map(|(epoch, _)| GroupEpoch::from(*epoch))
+    }
+
+    fn add_message_hash(&mut self, epoch: GroupEpoch, message: &SerializedMlsMessage) {
+        self.message_hashes
+            .entry(epoch.as_u64())
+            .or_default()
+            .insert(message_hash(message));
+    }
+
+    fn remove_old_epochs(&mut self, epoch: GroupEpoch) {
+        let oldest_retained_epoch = epoch.as_u64().saturating_sub(RETAINED_EPOCHS - 1);
+        self.commits = self.commits.split_off(&oldest_retained_epoch);
+        self.message_hashes = self.message_hashes.split_off(&oldest_retained_epoch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(index: usize) -> SerializedMlsMessage {
+        SerializedMlsMessage(index.to_be_bytes().to_vec())
+    }
+
+    #[test]
+    fn proposals_are_capped_per_epoch() {
+        let mut accepted_messages = AcceptedMessages::default();
+        let epoch = GroupEpoch::from(3);
+        for index in 0..MAX_PROPOSALS_PER_EPOCH {
+            assert!(!accepted_messages.proposal_limit_reached(epoch));
+            accepted_messages.add_proposal(epoch, &message(index));
+        }
+        assert!(accepted_messages.proposal_limit_reached(epoch));
+
+        let overflow = message(MAX_PROPOSALS_PER_EPOCH);
+        accepted_messages.add_proposal(epoch, &overflow);
+        assert_eq!(accepted_messages.accepted_epoch(&overflow), None);
+        assert_eq!(
+            accepted_messages.message_hashes[&epoch.as_u64()].len(),
+            MAX_PROPOSALS_PER_EPOCH
+        );
+        assert!(!accepted_messages.proposal_limit_reached(GroupEpoch::from(4)));
+    }
+
+    #[test]
+    fn proposals_of_old_epochs_are_dropped() {
+        let mut accepted_messages = AcceptedMessages::default();
+        accepted_messages.add_proposal(GroupEpoch::from(0), &message(0));
+        accepted_messages.add_proposal(GroupEpoch::from(RETAINED_EPOCHS), &message(1));
+        assert_eq!(accepted_messages.accepted_epoch(&message(0)), None);
+        assert_eq!(
+            accepted_messages.accepted_epoch(&message(1)),
+            Some(GroupEpoch::from(RETAINED_EPOCHS))
+        );
+    }
+}
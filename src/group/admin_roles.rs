@@ -0,0 +1,161 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Admin roles stored in a custom group context extension.
+//!
+//! If a group's context contains an [`AdminRolesExtension`], only admins may
+//! commit Remove or GroupContextExtensions proposals. Changes to the admin
+//! roles themselves additionally have to be proposed by an admin. Groups
+//! without the extension are not restricted, but introducing the extension
+//! into them has to be allowed by
+//! [`AssistPolicy::check_admin_roles_introduction`].
+
+use openmls::prelude::{Credential, Extension, Extensions, QueuedProposal, UnknownExtension};
+
+use crate::tls_codec::{
+    self, Deserialize as _, Serialize as _, TlsDeserialize, TlsSerialize, TlsSize,
+};
+
+use super::{policy::AssistPolicy, *};
+
+/// Extension type of the [`AdminRolesExtension`]. It lies in the range
+/// reserved for private use.
+pub const ADMIN_ROLES_EXTENSION_TYPE: u16 = 0xff00;
+
+/// Group context extension that lists the credentials of the group's
+/// admins. Admins are identified by their credential rather than their
+/// signature key, so that they keep their role when they update their leaf
+/// with a new signature key.
+#[derive(Debug, Clone, PartialEq, Eq, Default, TlsSerialize, TlsDeserialize, TlsSize)]
+pub struct AdminRolesExtension {
+    admins: Vec<Credential>,
+}
+
+impl AdminRolesExtension {
+    pub fn new(admins: Vec<Credential>) -> Self {
+        Self { admins }
+    }
+
+    pub fn admins(&self) -> &[Credential] {
+        &self.admins
+    }
+
+    /// Returns true if the given credential belongs to an admin.
+    pub fn is_admin(&self, credential: &Credential) -> bool {
+        self.admins.contains(credential)
+    }
+
+    /// Encode this extension as an [`Extension`] to be included in a group
+    /// context.
+    pub fn to_extension(&self) -> Result<Extension, tls_codec::Error> {
+        Ok(Extension::Unknown(
+            ADMIN_ROLES_EXTENSION_TYPE,
+            UnknownExtension(self.tls_serialize_detached()?),
+        ))
+    }
+
+    /// Decode the admin roles extension from the given extensions. Returns
+    /// `None` if there is none.
+    pub fn from_extensions(extensions: &Extensions) -> Result<Option<Self>, tls_codec::Error> {
+        extensions
+            .unknown(ADMIN_ROLES_EXTENSION_TYPE)
+            .map(|extension| Self::tls_deserialize_exact(&extension.0))
+            .transpose()
+    }
+}
+
+impl Group {
+    /// Returns the admin roles of the group, if it has any.
+    pub fn admin_roles(&self) -> Result<Option<AdminRolesExtension>, tls_codec::Error> {
+        AdminRolesExtension::from_extensions(self.public_group.group_context().extensions())
+    }
+
+    /// Check that the committer of the given commit has the admin rights
+    /// required by the commit's proposals. In groups without admin roles, the
+    /// introduction of admin roles is checked against `policy`.
+    pub(super) fn check_admin_rights(
+        &self,
+        staged_commit: &StagedCommit,
+        sender: &Sender,
+        committer_leaf: Option<&LeafNode>,
+        policy: &impl AssistPolicy,
+    ) -> Result<(), ProcessAssistedMessageError> {
+        let Some(admin_roles) = self
+            .admin_roles()
+            .map_err(|_| ProcessAssistedMessageError::InvalidAdminRoles)?
+        else {
+            return self.check_admin_roles_introduction(staged_commit, policy);
+        };
+        let is_admin = |leaf: Option<&LeafNode>| {
+            leaf.is_some_and(|leaf| admin_roles.is_admin(leaf.credential()))
+        };
+        let requires_admin = staged_commit.queued_proposals().any(|queued_proposal| {
+            match queued_proposal.proposal() {
+                // External commits may remove the joiner's previous leaf.
+                Proposal::Remove(_) => !matches!(sender, Sender::NewMemberCommit),
+                Proposal::GroupContextExtensions(_) => true,
+                _ => false,
+            }
+        });
+        if !requires_admin {
+            return Ok(());
+        }
+        if !is_admin(committer_leaf) {
+            return Err(ProcessAssistedMessageError::MissingAdminRights);
+        }
+        // Changes to the admin roles have to be proposed by an admin.
+        for queued_proposal in staged_commit.queued_proposals() {
+            let Proposal::GroupContextExtensions(group_context_extensions) =
+                queued_proposal.proposal()
+            else {
+                continue;
+            };
+            let new_admin_roles =
+                AdminRolesExtension::from_extensions(group_context_extensions.extensions())
+                    .map_err(|_| ProcessAssistedMessageError::InvalidAdminRoles)?;
+            if new_admin_roles.as_ref() == Some(&admin_roles) {
+                continue;
+            }
+            if !is_admin(self.proposer_leaf(queued_proposal)) {
+                return Err(ProcessAssistedMessageError::MissingAdminRights);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check every GroupContextExtensions proposal of the given commit that
+    /// introduces admin roles against `policy`.
+    fn check_admin_roles_introduction(
+        &self,
+        staged_commit: &StagedCommit,
+        policy: &impl AssistPolicy,
+    ) -> Result<(), ProcessAssistedMessageError> {
+        for queued_proposal in staged_commit.queued_proposals() {
+            let Proposal::GroupContextExtensions(group_context_extensions) =
+                queued_proposal.proposal()
+            else {
+                continue;
+            };
+            let Some(admin_roles) =
+                AdminRolesExtension::from_extensions(group_context_extensions.extensions())
+                    .map_err(|_| ProcessAssistedMessageError::InvalidAdminRoles)?
+            else {
+                continue;
+            };
+            policy.check_admin_roles_introduction(
+                &admin_roles,
+                self.proposer_leaf(queued_proposal),
+                self.public_group.group_context(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn proposer_leaf(&self, queued_proposal: &QueuedProposal) -> Option<&LeafNode> {
+        match queued_proposal.sender() {
+            Sender::Member(leaf_index) => self.public_group.leaf(*leaf_index),
+            _ => None,
+        }
+    }
+}
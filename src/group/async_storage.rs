@@ -0,0 +1,137 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::{
+    async_provider::{
+        AsyncMlsAssistProvider, AsyncMlsAssistStorageProvider, AsyncProviderError,
+        AsyncStorageError, StagingProvider, StagingStorage,
+    },
+    memory_provider::Codec,
+};
+
+use super::*;
+
+impl Group {
+    /// Async version of [`Group::new`]. The group's records are written to
+    /// storage in one step after the group has been created.
+    pub async fn new_async<Provider: AsyncMlsAssistProvider>(
+        provider: &Provider,
+        verifiable_group_info: VerifiableGroupInfo,
+        ratchet_tree: RatchetTreeIn,
+    ) -> Result<Self, CreationFromExternalError<AsyncProviderError<Provider>>> {
+        let staging_provider = StagingProvider {
+            provider,
+            storage: StagingStorage::default(),
+        };
+        let group = Self::new(&staging_provider, verifiable_group_info, ratchet_tree)?;
+        let group_id = group.group_info.group_context().group_id();
+        persist(&staging_provider, group_id)
+            .await
+            .map_err(CreationFromExternalError::WriteToStorageError)?;
+        Ok(group)
+    }
+
+    /// Async version of [`Group::load`]. All records of the group are read
+    /// from storage in one step.
+    pub async fn load_async<Provider: AsyncMlsAssistProvider>(
+        provider: &Provider,
+        group_id: &GroupId,
+    ) -> Result<Option<Self>, AsyncProviderError<Provider>> {
+        let group_id_bytes = encode_group_id::<Provider>(group_id)?;
+        let records = provider
+            .storage()
+            .read_group(&group_id_bytes)
+            .await
+            .map_err(AsyncStorageError::Storage)?;
+        if records.is_empty() {
+            return Ok(None);
+        }
+        let storage = StagingStorage::<Provider::Codec, _>::new(&group_id_bytes, records);
+        Self::load(&storage, group_id)
+    }
+
+    /// Async version of [`Group::delete`].
+    pub async fn delete_async<Provider: AsyncMlsAssistProvider>(
+        provider: &Provider,
+        group_id: &GroupId,
+    ) -> Result<(), AsyncProviderError<Provider>> {
+        let group_id_bytes = encode_group_id::<Provider>(group_id)?;
+        provider
+            .storage()
+            .delete_group(&group_id_bytes)
+            .await
+            .map_err(AsyncStorageError::Storage)
+    }
+
+    /// Async version of [`Group::accept_processed_message`]. The message is
+    /// accepted without any I/O and the resulting changes are written to
+    /// storage in one step afterwards. Since accepting a message only writes
+    /// records, none of the group's records have to be read for this.
+    ///
+    /// If an error is returned, the group must be loaded from storage again,
+    /// since its in-memory state may already reflect the message.
+    pub async fn accept_processed_message_async<Provider: AsyncMlsAssistProvider>(
+        &mut self,
+        provider: &Provider,
+        processed_message_plus: ProcessedAssistedMessagePlus,
+        retention_policy: &RetentionPolicy,
+    ) -> Result<AcceptOutcome, AcceptProcessedMessageError<AsyncProviderError<Provider>>> {
+        let staging_provider = StagingProvider {
+            provider,
+            storage: StagingStorage::default(),
+        };
+        let outcome = self.accept_processed_message(
+            &staging_provider,
+            processed_message_plus,
+            retention_policy,
+        )?;
+        let group_id = self.group_info.group_context().group_id();
+        persist(&staging_provider, group_id)
+            .await
+            .map_err(MergeCommitError::StorageError)?;
+        Ok(outcome)
+    }
+
+    /// Async version of [`Group::prune`]. The removed past group states are
+    /// deleted from storage in one step afterwards.
+    pub async fn prune_async<Provider: AsyncMlsAssistProvider>(
+        &mut self,
+        provider: &Provider,
+        retention_policy: &RetentionPolicy,
+    ) -> Result<EvictionStats, AsyncProviderError<Provider>> {
+        let staging_provider = StagingProvider {
+            provider,
+            storage: StagingStorage::default(),
+        };
+        let stats = self.prune(&staging_provider, retention_policy)?;
+        let group_id = self.group_info.group_context().group_id();
+        persist(&staging_provider, group_id).await?;
+        Ok(stats)
+    }
+}
+
+fn encode_group_id<Provider: AsyncMlsAssistProvider>(
+    group_id: &GroupId,
+) -> Result<Vec<u8>, AsyncProviderError<Provider>> {
+    <Provider::Codec as Codec>::to_vec(group_id).map_err(AsyncStorageError::Codec)
+}
+
+/// Write the changes made to the records of the given group in the staging
+/// storage to the async storage.
+async fn persist<Provider: AsyncMlsAssistProvider>(
+    staging_provider: &StagingProvider<'_, Provider>,
+    group_id: &GroupId,
+) -> Result<(), AsyncProviderError<Provider>> {
+    let group_id_bytes = encode_group_id::<Provider>(group_id)?;
+    let changes = staging_provider.storage.take_changes(&group_id_bytes);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    staging_provider
+        .provider
+        .storage()
+        .write_group(&group_id_bytes, changes)
+        .await
+        .map_err(AsyncStorageError::Storage)
+}
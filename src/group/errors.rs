@@ -2,12 +2,22 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use openmls::prelude::ProcessMessageError;
+use openmls::{
+    group::MergeCommitError,
+    prelude::{
+        GroupEpoch, KeyPackageRef, ProcessMessageError, ProposeAddMemberError,
+        ProposeRemoveMemberError,
+    },
+};
 use openmls_traits::{
     public_storage::PublicStorageProvider as PublicStorageProviderTrait, storage::CURRENT_VERSION,
 };
 use thiserror::Error;
 
+use crate::{messages::SerializedMlsMessage, tls_codec};
+
+use super::policy::PolicyViolation;
+
 #[cfg(doc)]
 use openmls::prelude::{GroupContext, ProcessedMessage, group_info::GroupInfo};
 
@@ -35,9 +45,126 @@ pub enum ProcessAssistedMessageError {
     /// Unknown sender.
     #[error("Unknown sender.")]
     UnknownSender,
+    /// External senders may only send Add, Remove and GroupContextExtensions
+    /// proposals.
+    #[error("External senders may only send Add, Remove and GroupContextExtensions proposals.")]
+    InvalidExternalProposal,
     /// [`GroupContext`] is inconsistent between [`ProcessedMessage`] and [`GroupInfo`].
     #[error("[`GroupContext`] is inconsistent between [`ProcessedMessage`] and [`GroupInfo`].")]
     InconsistentGroupContext,
+    /// Another commit was already accepted for the epoch of the commit.
+    #[error("Another commit was already accepted for the epoch of the commit.")]
+    CommitConflict {
+        /// The commit that was accepted for the epoch of the rejected commit.
+        winning_commit: SerializedMlsMessage,
+        /// The current epoch of the group.
+        current_epoch: GroupEpoch,
+    },
+    /// The maximum number of proposals was already accepted in the current
+    /// epoch.
+    #[error("Too many proposals in the current epoch.")]
+    TooManyProposals,
+    /// The committer lacks the admin rights required for the commit.
+    #[error("The committer lacks the admin rights required for the commit.")]
+    MissingAdminRights,
+    /// The admin roles extension could not be decoded.
+    #[error("The admin roles extension could not be decoded.")]
+    InvalidAdminRoles,
+    /// See [`PolicyViolation`] for more details.
+    #[error(transparent)]
+    PolicyViolation(#[from] PolicyViolation),
+}
+
+/// Accept processed message error
+#[derive(Error, Debug)]
+pub enum AcceptProcessedMessageError<E> {
+    /// Application messages can't be accepted, since they can't be processed
+    /// by the assisted group.
+    #[error("Application messages can't be accepted.")]
+    ApplicationMessage,
+    /// See [`MergeCommitError`] for more details.
+    #[error(transparent)]
+    MergeCommitError(#[from] MergeCommitError<E>),
+}
+
+/// Welcome validation error
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum WelcomeValidationError {
+    /// The ciphersuite of the Welcome doesn't match the group's.
+    #[error("The ciphersuite of the Welcome doesn't match the group's.")]
+    CiphersuiteMismatch,
+    /// The Welcome is addressed to a key package that was not added by the
+    /// commit.
+    #[error("The Welcome is addressed to a key package that was not added by the commit.")]
+    UnknownJoiner(KeyPackageRef),
+    /// A key package added by the commit has no entry in the Welcome.
+    #[error("A key package added by the commit has no entry in the Welcome.")]
+    MissingJoiner(KeyPackageRef),
+    /// See [`LibraryError`] for more details.
+    #[error(transparent)]
+    LibraryError(#[from] LibraryError),
+}
+
+/// External proposal error
+#[derive(Error, Debug)]
+pub enum ExternalProposalError<E, F> {
+    /// The given signature key is not listed in the group's external senders
+    /// extension.
+    #[error("The given signature key is not listed in the group's external senders extension.")]
+    UnknownExternalSender,
+    /// The member to be removed is not part of the group.
+    #[error("The member to be removed is not part of the group.")]
+    UnknownMember,
+    /// See [`ProposeRemoveMemberError`] for more details.
+    #[error(transparent)]
+    ProposeRemoveMemberError(ProposeRemoveMemberError<F>),
+    /// See [`ProposeAddMemberError`] for more details.
+    #[error(transparent)]
+    ProposeAddMemberError(ProposeAddMemberError<F>),
+    /// See [`tls_codec::Error`] for more details.
+    #[error(transparent)]
+    TlsCodecError(#[from] tls_codec::Error),
+    /// See [`ProcessAssistedMessageError`] for more details.
+    #[error(transparent)]
+    ProcessAssistedMessageError(#[from] ProcessAssistedMessageError),
+    /// See [`AcceptProcessedMessageError`] for more details.
+    #[error(transparent)]
+    AcceptProcessedMessageError(#[from] AcceptProcessedMessageError<E>),
+}
+
+/// Recipients error
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum RecipientsError {
+    /// The private message was sent in a different epoch than the group's
+    /// current one, whose members are unknown.
+    #[error("The private message was sent in a different epoch than the group's current one.")]
+    WrongEpoch {
+        /// The epoch of the private message.
+        message_epoch: GroupEpoch,
+        /// The current epoch of the group.
+        current_epoch: GroupEpoch,
+    },
+}
+
+/// Past group state request error
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum PastGroupStateRequestError {
+    /// The request is for a different group.
+    #[error("The request is for a different group.")]
+    WrongGroup,
+    /// The request was made in a different epoch than the group's current
+    /// one.
+    #[error("The request was made in a different epoch than the group's current one.")]
+    WrongEpoch {
+        /// The current epoch of the group.
+        current_epoch: GroupEpoch,
+    },
+    /// The request is not signed by the joiner.
+    #[error("The request is not signed by the joiner.")]
+    InvalidSignature,
+    /// The timestamp of the request is too far from the current time.
+    #[error("The timestamp of the request is too far from the current time.")]
+    StaleRequest,
 }
 
 #[derive(Error, Debug, PartialEq, Clone)]
@@ -0,0 +1,121 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use openmls::{
+    prelude::{ExternalProposal, ExternalSender, KeyPackage, MlsMessageOut},
+    storage::OpenMlsProvider,
+};
+use openmls_traits::signatures::Signer;
+
+use crate::tls_codec::{DeserializeBytes as _, Serialize as _};
+
+use super::{errors::ExternalProposalError, *};
+
+impl Group {
+    /// Create an external Remove proposal for the member with the given leaf
+    /// index, signed with `signer`. The signer must be listed in the group's
+    /// external senders extension as `external_sender`. `MlsProvider` is the
+    /// OpenMLS provider the proposal is created with.
+    ///
+    /// The proposal is accepted like a proposal sent by a client, so it is
+    /// put into the group's proposal store and can be covered by a
+    /// subsequent commit. Returns the proposal as an [`MlsMessageOut`] to be
+    /// distributed to the group members.
+    pub fn propose_external_remove<Provider: MlsAssistProvider, MlsProvider: OpenMlsProvider>(
+        &mut self,
+        provider: &Provider,
+        signer: &impl Signer,
+        external_sender: &ExternalSender,
+        removed: LeafNodeIndex,
+        retention_policy: &RetentionPolicy,
+    ) -> Result<
+        MlsMessageOut,
+        ExternalProposalError<StorageError<Provider::Storage>, MlsProvider::StorageError>,
+    > {
+        if self.leaf(removed).is_none() {
+            return Err(ExternalProposalError::UnknownMember);
+        }
+        let sender_index = self.external_sender_index(external_sender)?;
+        let mls_message = ExternalProposal::new_remove::<MlsProvider>(
+            removed,
+            self.public_group.group_id().clone(),
+            self.epoch(),
+            signer,
+            sender_index,
+        )
+        .map_err(ExternalProposalError::ProposeRemoveMemberError)?;
+        self.queue_external_proposal(provider, mls_message, retention_policy)
+    }
+
+    /// Create an external Add proposal for the given key package, signed with
+    /// `signer`. The signer must be listed in the group's external senders
+    /// extension as `external_sender`. `MlsProvider` is the OpenMLS provider
+    /// the proposal is created with.
+    ///
+    /// The proposal is accepted like a proposal sent by a client, so it is
+    /// put into the group's proposal store and can be covered by a
+    /// subsequent commit. Returns the proposal as an [`MlsMessageOut`] to be
+    /// distributed to the group members.
+    pub fn propose_external_add<Provider: MlsAssistProvider, MlsProvider: OpenMlsProvider>(
+        &mut self,
+        provider: &Provider,
+        signer: &impl Signer,
+        external_sender: &ExternalSender,
+        key_package: KeyPackage,
+        retention_policy: &RetentionPolicy,
+    ) -> Result<
+        MlsMessageOut,
+        ExternalProposalError<StorageError<Provider::Storage>, MlsProvider::StorageError>,
+    > {
+        let sender_index = self.external_sender_index(external_sender)?;
+        let mls_message = ExternalProposal::new_add::<MlsProvider>(
+            key_package,
+            self.public_group.group_id().clone(),
+            self.epoch(),
+            signer,
+            sender_index,
+        )
+        .map_err(ExternalProposalError::ProposeAddMemberError)?;
+        self.queue_external_proposal(provider, mls_message, retention_policy)
+    }
+}
+
+// Helper functions
+impl Group {
+    fn external_sender_index<E, F>(
+        &self,
+        external_sender: &ExternalSender,
+    ) -> Result<SenderExtensionIndex, ExternalProposalError<E, F>> {
+        self.public_group
+            .group_context()
+            .extensions()
+            .external_senders()
+            .and_then(|external_senders| {
+                external_senders
+                    .iter()
+                    .position(|candidate| candidate == external_sender)
+            })
+            .map(|index| SenderExtensionIndex::new(index as u32))
+            .ok_or(ExternalProposalError::UnknownExternalSender)
+    }
+
+    /// Process and accept the given proposal like one that was sent by a
+    /// client. This puts it into the proposal store and records it as
+    /// accepted in the current epoch.
+    fn queue_external_proposal<Provider: MlsAssistProvider, F>(
+        &mut self,
+        provider: &Provider,
+        mls_message: MlsMessageOut,
+        retention_policy: &RetentionPolicy,
+    ) -> Result<MlsMessageOut, ExternalProposalError<StorageError<Provider::Storage>, F>> {
+        // An assisted message without a group info.
+        let mut serialized_message = mls_message.tls_serialize_detached()?;
+        serialized_message.push(0);
+        let assisted_message = AssistedMessageIn::tls_deserialize_exact_bytes(&serialized_message)?;
+        let processed_message =
+            self.process_assisted_message(provider.crypto(), assisted_message)?;
+        self.accept_processed_message(provider, processed_message, retention_policy)?;
+        Ok(mls_message)
+    }
+}
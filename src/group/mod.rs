@@ -2,34 +2,65 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::collections::HashMap;
+
 use crate::{
-    messages::{AssistedGroupInfoIn, AssistedMessageIn, SerializedMlsMessage},
-    provider_traits::{MlsAssistProvider, MlsAssistStorageProvider},
+    messages::{
+        AssistedGroupInfoIn, AssistedMessageIn, JoinerId, PastGroupStateRequest,
+        SerializedMlsMessage,
+    },
+    provider_traits::{Clock, MlsAssistProvider, MlsAssistStorageProvider},
 };
-use chrono::Duration;
 use errors::StorageError;
 use openmls::{
     framing::PrivateMessageIn,
     group::{GroupId, MergeCommitError},
     prelude::{
-        ConfirmationTag, CreationFromExternalError, GroupEpoch, LeafNodeIndex, Member,
-        OpenMlsSignaturePublicKey, ProcessedMessage, ProcessedMessageContent, ProposalStore,
-        PublicGroup, Sender, SignaturePublicKey, StagedCommit,
+        ConfirmationTag, CreationFromExternalError, ExternalSender, GroupEpoch, KeyPackageRef,
+        LeafNodeIndex, Member, OpenMlsSignaturePublicKey, ProcessedMessage,
+        ProcessedMessageContent, Proposal, ProposalStore, PublicGroup, QueuedProposal, Sender,
+        SenderExtensionIndex, SignaturePublicKey, StagedCommit,
         group_info::{GroupInfo, VerifiableGroupInfo},
     },
     treesync::{LeafNode, RatchetTree, RatchetTreeIn},
 };
 
-use self::{errors::ProcessAssistedMessageError, past_group_states::PastGroupStates};
+use self::{
+    accepted_messages::AcceptedMessages,
+    errors::{
+        AcceptProcessedMessageError, PastGroupStateRequestError, ProcessAssistedMessageError,
+    },
+    past_group_states::{EvictionStats, PastGroupStates, RetentionPolicy},
+};
 
+mod accepted_messages;
+pub mod admin_roles;
+#[cfg(feature = "async")]
+mod async_storage;
 pub mod errors;
-mod past_group_states;
+mod external_proposals;
+pub mod past_group_states;
+pub mod policy;
 pub mod process;
+pub mod recipients;
+#[cfg(test)]
+mod tests;
+mod tree_chunks;
+mod welcome;
+
+/// Default maximum difference in seconds between the timestamp of a
+/// [`PastGroupStateRequest`] and the current time. See
+/// [`RetentionPolicy::with_request_validity`].
+pub const PAST_GROUP_STATE_REQUEST_VALIDITY: i64 = 5 * 60;
 
 pub struct Group {
     public_group: PublicGroup,
     group_info: GroupInfo,
     past_group_states: PastGroupStates,
+    accepted_messages: AcceptedMessages,
+    /// The proposals in the proposal store of the public group, which can't
+    /// be accessed without storage.
+    queued_proposals: Vec<QueuedProposal>,
 }
 
 impl Group {
@@ -39,28 +70,38 @@ impl Group {
         verifiable_group_info: VerifiableGroupInfo,
         ratchet_tree: RatchetTreeIn,
     ) -> Result<Self, CreationFromExternalError<StorageError<Provider::Storage>>> {
-        let (public_group, group_info) = PublicGroup::from_external(
-            provider.crypto(),
-            provider.storage(),
-            ratchet_tree,
-            verifiable_group_info,
-            ProposalStore::default(),
-        )?;
-        let group_id = group_info.group_context().group_id();
-        let past_group_states = PastGroupStates::default();
-        provider
-            .storage()
-            .write_group_info(group_id, &group_info)
-            .map_err(CreationFromExternalError::WriteToStorageError)?;
+        // Write the public group and the MLS-assist specific state in one
+        // transaction, so that a failure doesn't leave a partial group behind.
         provider
             .storage()
-            .write_past_group_states(group_id, &past_group_states)
-            .map_err(CreationFromExternalError::WriteToStorageError)?;
-        Ok(Self {
-            group_info,
-            public_group,
-            past_group_states,
-        })
+            .with_transaction(|| {
+                let (public_group, group_info) = PublicGroup::from_external(
+                    provider.crypto(),
+                    provider.storage(),
+                    ratchet_tree,
+                    verifiable_group_info,
+                    ProposalStore::default(),
+                )?;
+                let group_id = group_info.group_context().group_id();
+                let past_group_states = PastGroupStates::default();
+                let accepted_messages = AcceptedMessages::default();
+                provider
+                    .storage()
+                    .write_group_info(group_id, &group_info)
+                    .map_err(CreationFromExternalError::WriteToStorageError)?;
+                provider
+                    .storage()
+                    .write_accepted_messages(group_id, &accepted_messages)
+                    .map_err(CreationFromExternalError::WriteToStorageError)?;
+                Ok(Self {
+                    group_info,
+                    public_group,
+                    past_group_states,
+                    accepted_messages,
+                    queued_proposals: vec![],
+                })
+            })
+            .map_err(CreationFromExternalError::WriteToStorageError)?
     }
 
     pub fn load<StorageProvider: MlsAssistStorageProvider>(
@@ -68,19 +109,28 @@ impl Group {
         group_id: &GroupId,
     ) -> Result<Option<Self>, StorageError<StorageProvider>> {
         let group_info_option = provider.read_group_info(group_id)?;
-        let past_group_states_option = provider.read_past_group_states(group_id)?;
+        let past_group_states = PastGroupStates::load(provider, group_id)?;
+        // Groups created before accepted messages were recorded don't have
+        // any stored.
+        let accepted_messages = provider
+            .read_accepted_messages(group_id)?
+            .unwrap_or_default();
         let public_group_option = PublicGroup::load(provider, group_id)?;
-        let (Some(group_info), Some(past_group_states), Some(public_group)) = (
-            group_info_option,
-            past_group_states_option,
-            public_group_option,
-        ) else {
+        let (Some(group_info), Some(public_group)) = (group_info_option, public_group_option)
+        else {
             return Ok(None);
         };
+        let queued_proposals = public_group
+            .queued_proposals(provider)?
+            .into_iter()
+            .map(|(_, queued_proposal)| queued_proposal)
+            .collect();
         let group = Self {
             group_info,
             public_group,
             past_group_states,
+            accepted_messages,
+            queued_proposals,
         };
         Ok(Some(group))
     }
@@ -89,56 +139,172 @@ impl Group {
         provider: &StorageProvider,
         group_id: &GroupId,
     ) -> Result<(), StorageError<StorageProvider>> {
-        provider.delete_group_info(group_id)?;
-        provider.delete_past_group_states(group_id)?;
-        provider.delete_tree(group_id)?;
-        provider.delete_confirmation_tag(group_id)?;
-        provider.delete_context(group_id)?;
-        provider.delete_interim_transcript_hash(group_id)?;
-        Ok(())
+        provider.with_transaction(|| {
+            provider.delete_group_info(group_id)?;
+            provider.delete_past_group_states(group_id)?;
+            provider.delete_accepted_messages(group_id)?;
+            provider.delete_tree(group_id)?;
+            provider.delete_confirmation_tag(group_id)?;
+            provider.delete_context(group_id)?;
+            provider.delete_interim_transcript_hash(group_id)?;
+            Ok(())
+        })?
     }
 
-    pub fn accept_processed_message<StorageProvider: MlsAssistStorageProvider>(
+    /// Accept the given processed message. Returns the changes to the group's
+    /// membership caused by the message.
+    ///
+    /// All storage writes happen in a single transaction, so either all of
+    /// them land or none do. If an error is returned, the group must be
+    /// loaded from storage again, since its in-memory state may already
+    /// reflect the message.
+    pub fn accept_processed_message<Provider: MlsAssistProvider>(
         &mut self,
-        provider: &StorageProvider,
-        processed_assisted_message: ProcessedAssistedMessage,
-        expiration_time: Duration,
-    ) -> Result<(), MergeCommitError<StorageError<StorageProvider>>> {
+        provider: &Provider,
+        processed_message_plus: ProcessedAssistedMessagePlus,
+        retention_policy: &RetentionPolicy,
+    ) -> Result<AcceptOutcome, AcceptProcessedMessageError<StorageError<Provider::Storage>>> {
+        provider
+            .storage()
+            .with_transaction(|| {
+                self.apply_processed_message(provider, processed_message_plus, retention_policy)
+            })
+            .map_err(MergeCommitError::StorageError)?
+    }
+
+    fn apply_processed_message<Provider: MlsAssistProvider>(
+        &mut self,
+        provider: &Provider,
+        processed_message_plus: ProcessedAssistedMessagePlus,
+        retention_policy: &RetentionPolicy,
+    ) -> Result<AcceptOutcome, AcceptProcessedMessageError<StorageError<Provider::Storage>>> {
+        let ProcessedAssistedMessagePlus {
+            processed_assisted_message,
+            serialized_mls_message,
+        } = processed_message_plus;
         let processed_message = match processed_assisted_message {
-            ProcessedAssistedMessage::NonCommit(processed_message) => processed_message,
+            ProcessedAssistedMessage::NonCommit(processed_message)
+            | ProcessedAssistedMessage::ExternalProposal(processed_message, _) => {
+                self.accepted_messages
+                    .add_proposal(self.epoch(), &serialized_mls_message);
+                processed_message
+            }
             ProcessedAssistedMessage::Commit(processed_message, group_info) => {
                 self.group_info = group_info;
+                // Remember the commit, so that competing commits for the same
+                // epoch can be answered with it.
+                self.accepted_messages
+                    .add_commit(self.epoch(), serialized_mls_message);
                 processed_message
             }
-            ProcessedAssistedMessage::PrivateMessage(_) => return Ok(()),
+            ProcessedAssistedMessage::PrivateMessage(_)
+            | ProcessedAssistedMessage::Duplicate(_) => {
+                return Ok(AcceptOutcome::unchanged(self.epoch()));
+            }
         };
-        let added_potential_joiners = match processed_message.into_content() {
+        let sender = processed_message.sender().clone();
+        let (added_potential_joiners, mut outcome) = match processed_message.into_content() {
             ProcessedMessageContent::StagedCommitMessage(staged_commit) => {
                 // We want to add a new state for members that were added to the
                 // group via an Add proposal.
                 let added_potential_joiners = staged_commit
                     .add_proposals()
                     .map(|add_proposal| {
-                        add_proposal
-                            .add_proposal()
-                            .key_package()
-                            .leaf_node()
-                            .signature_key()
-                            .clone()
+                        let key_package = add_proposal.add_proposal().key_package();
+                        let signature_key = key_package.leaf_node().signature_key().clone();
+                        key_package
+                            .hash_ref(provider.crypto())
+                            .map(|key_package_ref| (key_package_ref, signature_key))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(MergeCommitError::LibraryError)?;
+
+                // Collect the membership changes while we still have the
+                // commit and the state of the previous epoch.
+                let mut members = self
+                    .members()
+                    .map(|member| (member.index, member))
+                    .collect::<HashMap<_, _>>();
+                let removed_members = staged_commit
+                    .remove_proposals()
+                    .filter_map(|remove_proposal| {
+                        members.remove(&remove_proposal.remove_proposal().removed())
                     })
                     .collect();
+                let mut added_signature_keys = added_potential_joiners
+                    .iter()
+                    .map(|(_, signature_key)| signature_key.clone())
+                    .collect::<Vec<_>>();
+                let mut updated_leaves = staged_commit
+                    .update_proposals()
+                    .filter_map(|update_proposal| match update_proposal.sender() {
+                        Sender::Member(leaf_index) => Some(*leaf_index),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                match (&sender, staged_commit.update_path_leaf_node()) {
+                    (Sender::Member(leaf_index), Some(_)) => updated_leaves.push(*leaf_index),
+                    (Sender::NewMemberCommit, Some(leaf_node)) => {
+                        added_signature_keys.push(leaf_node.signature_key().clone())
+                    }
+                    _ => {}
+                }
 
-                self.public_group.merge_commit(provider, *staged_commit)?;
-                added_potential_joiners
+                self.public_group
+                    .merge_commit(provider.storage(), *staged_commit)?;
+                // Merging the commit empties the proposal store.
+                self.queued_proposals.clear();
+
+                // Read the leaves the added clients were placed at from the
+                // merged tree.
+                let members_by_signature_key = self
+                    .members()
+                    .map(|member| (member.signature_key.clone(), member))
+                    .collect::<HashMap<_, _>>();
+                let joiners = added_potential_joiners
+                    .iter()
+                    .filter_map(|(key_package_ref, signature_key)| {
+                        members_by_signature_key
+                            .get(signature_key.as_slice())
+                            .map(|member| (key_package_ref.clone(), member.index))
+                    })
+                    .collect();
+                let outcome = AcceptOutcome {
+                    epoch: self.epoch(),
+                    added_members: added_signature_keys
+                        .iter()
+                        .filter_map(|signature_key| {
+                            members_by_signature_key
+                                .get(signature_key.as_slice())
+                                .cloned()
+                        })
+                        .collect(),
+                    joiners,
+                    removed_members,
+                    updated_members: self
+                        .members()
+                        .filter(|member| updated_leaves.contains(&member.index))
+                        .collect(),
+                    external_join: matches!(sender, Sender::NewMemberCommit),
+                    past_group_state_evictions: EvictionStats::default(),
+                };
+                (added_potential_joiners, outcome)
             }
-            ProcessedMessageContent::ProposalMessage(proposal) => {
+            ProcessedMessageContent::ProposalMessage(proposal)
+            | ProcessedMessageContent::ExternalJoinProposalMessage(proposal) => {
+                // External join proposals are queued like any other proposal.
+                // Their Add proposal contains the joiner's key package, so the
+                // joiner is picked up as a potential joiner once a commit
+                // covers the proposal.
                 self.public_group
-                    .add_proposal(provider, *proposal)
+                    .add_proposal(provider.storage(), (*proposal).clone())
                     .map_err(MergeCommitError::StorageError)?;
-                vec![]
+                self.queued_proposals.push(*proposal);
+                (vec![], AcceptOutcome::unchanged(self.epoch()))
+            }
+            ProcessedMessageContent::ApplicationMessage(_) => {
+                return Err(AcceptProcessedMessageError::ApplicationMessage);
             }
-            ProcessedMessageContent::ApplicationMessage(_)
-            | ProcessedMessageContent::ExternalJoinProposalMessage(_) => todo!(),
         };
         // Check if any potential joiners were added.
         self.past_group_states.add_state(
@@ -147,18 +313,31 @@ impl Group {
             self.public_group.group_context().epoch(),
             self.public_group.export_ratchet_tree(),
             &added_potential_joiners,
+            &outcome.joiners,
+            provider.clock().now(),
         );
-        // Check if any past group state has expired.
+        // Removed members may no longer obtain past group states they were
+        // added in.
         self.past_group_states
-            .remove_expired_states(expiration_time);
+            .remove_joiners(&outcome.removed_members);
+        // Check if any past group state has expired or exceeds the limits of
+        // the retention policy.
+        outcome.past_group_state_evictions = self
+            .past_group_states
+            .enforce_retention_policy(retention_policy, provider.clock().now());
         let group_id = self.group_info.group_context().group_id();
         provider
+            .storage()
             .write_group_info(group_id, self.group_info())
             .map_err(MergeCommitError::StorageError)?;
+        self.past_group_states
+            .write(provider.storage(), group_id)
+            .map_err(MergeCommitError::StorageError)?;
         provider
-            .write_past_group_states(group_id, &self.past_group_states)
+            .storage()
+            .write_accepted_messages(group_id, &self.accepted_messages)
             .map_err(MergeCommitError::StorageError)?;
-        Ok(())
+        Ok(outcome)
     }
 
     pub fn group_info(&self) -> &GroupInfo {
@@ -173,15 +352,114 @@ impl Group {
         self.public_group.group_context().epoch()
     }
 
-    /// Get the nodes of the past group state with the given epoch for the given
-    /// joiner. Returns `None` if there is no past group state for that epoch
-    /// and the given joiner.
-    pub fn past_group_state(
+    /// Get the nodes of the past group state requested by a joiner. The
+    /// request must be signed by the joiner and made in the group's current
+    /// epoch, and its timestamp must be within the request validity of the
+    /// given retention policy. Joiners can identify themselves either by
+    /// their signature key or by the ref of the key package they were added
+    /// with, as listed in the Welcome. Returns
+    /// `None` if there is no past group state for the requested epoch and the
+    /// joiner or if it has expired according to the given retention policy.
+    pub fn past_group_state<Provider: MlsAssistProvider>(
+        &self,
+        provider: &Provider,
+        request: &PastGroupStateRequest,
+        retention_policy: &RetentionPolicy,
+    ) -> Result<Option<RatchetTreeIn>, PastGroupStateRequestError> {
+        let joiner = self.verify_past_group_state_request(provider, request, retention_policy)?;
+        Ok(self.past_group_states.get_for_joiner(
+            &request.epoch(),
+            &joiner,
+            retention_policy.max_age(),
+            provider.clock().now(),
+        ))
+    }
+
+    /// Like [`Self::past_group_state`], but returns the TLS-serialized nodes
+    /// as they are encoded in the `ratchet_tree` extension, ready to be sent
+    /// to the joiner. Past trees are stored in this encoding, so serving them
+    /// doesn't require encoding them again.
+    pub fn past_group_state_bytes<Provider: MlsAssistProvider>(
+        &self,
+        provider: &Provider,
+        request: &PastGroupStateRequest,
+        retention_policy: &RetentionPolicy,
+    ) -> Result<Option<Vec<u8>>, PastGroupStateRequestError> {
+        let joiner = self.verify_past_group_state_request(provider, request, retention_policy)?;
+        Ok(self.past_group_states.tree_bytes_for_joiner(
+            &request.epoch(),
+            &joiner,
+            retention_policy.max_age(),
+            provider.clock().now(),
+        ))
+    }
+
+    /// Verify the given request and return the signature key of the joiner.
+    ///
+    /// A joiner identified by a key package that was not added in the
+    /// requested epoch yields the same error as an invalid signature, so that
+    /// requests don't reveal which key packages were added in which epoch.
+    fn verify_past_group_state_request<Provider: MlsAssistProvider>(
+        &self,
+        provider: &Provider,
+        request: &PastGroupStateRequest,
+        retention_policy: &RetentionPolicy,
+    ) -> Result<SignaturePublicKey, PastGroupStateRequestError> {
+        let group_context = self.group_info.group_context();
+        if request.group_id() != group_context.group_id() {
+            return Err(PastGroupStateRequestError::WrongGroup);
+        }
+        // Binding the current epoch keeps requests from being replayed once
+        // the group has moved on.
+        if request.current_epoch() != group_context.epoch() {
+            return Err(PastGroupStateRequestError::WrongEpoch {
+                current_epoch: group_context.epoch(),
+            });
+        }
+        // Within the request validity, the request may be replayed. That's
+        // accepted, as it only yields the tree the joiner is entitled to.
+        let now = provider.clock().now();
+        let is_fresh = request.timestamp().is_some_and(|timestamp| {
+            (now - timestamp).abs() <= retention_policy.request_validity()
+        });
+        if !is_fresh {
+            return Err(PastGroupStateRequestError::StaleRequest);
+        }
+        let joiner = match request.joiner() {
+            JoinerId::SignatureKey(signature_key) => Some(signature_key),
+            JoinerId::KeyPackageRef(key_package_ref) => self
+                .past_group_states
+                .joiner_signature_key(&request.epoch(), key_package_ref),
+        };
+        let signature_scheme = group_context.ciphersuite().signature_algorithm();
+        match joiner {
+            Some(joiner)
+                if request.verify_signature(provider.crypto(), signature_scheme, joiner) =>
+            {
+                Ok(joiner.clone())
+            }
+            _ => Err(PastGroupStateRequestError::InvalidSignature),
+        }
+    }
+
+    /// Remove past group states according to the given retention policy
+    /// without having to accept a message. Returns statistics on the removed
+    /// past group states.
+    pub fn prune<Provider: MlsAssistProvider>(
         &mut self,
-        epoch: &GroupEpoch,
-        joiner: &SignaturePublicKey,
-    ) -> Option<&RatchetTree> {
-        self.past_group_states.get_for_joiner(epoch, joiner)
+        provider: &Provider,
+        retention_policy: &RetentionPolicy,
+    ) -> Result<EvictionStats, StorageError<Provider::Storage>> {
+        // Delete the records of the removed past group states in one
+        // transaction, so that a failure doesn't leave some of them behind.
+        provider.storage().with_transaction(|| {
+            let stats = self
+                .past_group_states
+                .enforce_retention_policy(retention_policy, provider.clock().now());
+            let group_id = self.group_info.group_context().group_id();
+            self.past_group_states.write(provider.storage(), group_id)?;
+            Ok(stats)
+        })?
     }
 
     pub fn leaf(&self, leaf_index: LeafNodeIndex) -> Option<&LeafNode> {
@@ -191,6 +469,56 @@ impl Group {
     pub fn members(&self) -> impl Iterator<Item = Member> + '_ {
         self.public_group.members()
     }
+
+    /// Returns the external sender with the given index from the group's
+    /// external senders extension, if there is one.
+    pub fn external_sender(&self, sender_index: &SenderExtensionIndex) -> Option<&ExternalSender> {
+        self.public_group
+            .group_context()
+            .extensions()
+            .external_senders()?
+            .iter()
+            .enumerate()
+            .find(|(index, _)| SenderExtensionIndex::new(*index as u32) == *sender_index)
+            .map(|(_, external_sender)| external_sender)
+    }
+}
+
+/// Changes to the group caused by an accepted message.
+#[derive(Debug, Clone)]
+pub struct AcceptOutcome {
+    /// The epoch of the group after accepting the message.
+    pub epoch: GroupEpoch,
+    /// Members added by the commit, including a member that joined via an
+    /// external commit.
+    pub added_members: Vec<Member>,
+    /// The leaves the clients added via Add proposals were placed at, indexed
+    /// by the [`KeyPackageRef`] of their key package. Used to validate the
+    /// commit's Welcome (see [`Group::validate_welcome`]).
+    pub joiners: HashMap<KeyPackageRef, LeafNodeIndex>,
+    /// Members removed by the commit, as they were before the commit.
+    pub removed_members: Vec<Member>,
+    /// Members whose leaves were updated by the commit, either via an Update
+    /// proposal or because they were the committer and sent a path.
+    pub updated_members: Vec<Member>,
+    /// True if the commit was an external commit.
+    pub external_join: bool,
+    /// Past group states removed according to the retention policy.
+    pub past_group_state_evictions: EvictionStats,
+}
+
+impl AcceptOutcome {
+    fn unchanged(epoch: GroupEpoch) -> Self {
+        Self {
+            epoch,
+            added_members: vec![],
+            joiners: HashMap::new(),
+            removed_members: vec![],
+            updated_members: vec![],
+            external_join: false,
+            past_group_state_evictions: EvictionStats::default(),
+        }
+    }
 }
 
 pub struct ProcessedAssistedMessagePlus {
@@ -201,16 +529,42 @@ pub struct ProcessedAssistedMessagePlus {
 pub enum ProcessedAssistedMessage {
     PrivateMessage(PrivateMessageIn),
     NonCommit(ProcessedMessage),
+    /// A proposal sent by one of the group's external senders, together with
+    /// the matching entry of the external senders extension.
+    ExternalProposal(ProcessedMessage, ExternalSender),
     Commit(ProcessedMessage, GroupInfo),
+    /// A commit or proposal that was already accepted in the given epoch.
+    Duplicate(GroupEpoch),
 }
 
 impl ProcessedAssistedMessage {
     pub fn sender(&self) -> Option<&Sender> {
         match self {
-            ProcessedAssistedMessage::NonCommit(pm) | ProcessedAssistedMessage::Commit(pm, _) => {
-                Some(pm.sender())
+            ProcessedAssistedMessage::NonCommit(pm)
+            | ProcessedAssistedMessage::ExternalProposal(pm, _)
+            | ProcessedAssistedMessage::Commit(pm, _) => Some(pm.sender()),
+            ProcessedAssistedMessage::PrivateMessage(_)
+            | ProcessedAssistedMessage::Duplicate(_) => None,
+        }
+    }
+
+    /// Returns the signature key of the sender if the message is a proposal
+    /// sent by a [`Sender::NewMemberProposal`], i.e. a client asking to join
+    /// the group.
+    pub fn new_member_proposal_signature_key(&self) -> Option<&SignaturePublicKey> {
+        let ProcessedAssistedMessage::NonCommit(processed_message) = self else {
+            return None;
+        };
+        let ProcessedMessageContent::ExternalJoinProposalMessage(queued_proposal) =
+            processed_message.content()
+        else {
+            return None;
+        };
+        match queued_proposal.proposal() {
+            Proposal::Add(add_proposal) => {
+                Some(add_proposal.key_package().leaf_node().signature_key())
             }
-            ProcessedAssistedMessage::PrivateMessage(_) => None,
+            _ => None,
         }
     }
 }
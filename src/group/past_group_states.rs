@@ -2,39 +2,301 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    ops::AddAssign,
+};
 
 use chrono::{DateTime, Duration, Utc};
 use openmls::{
-    prelude::{GroupEpoch, SignaturePublicKey},
-    treesync::RatchetTree,
+    group::GroupId,
+    prelude::{GroupEpoch, KeyPackageRef, LeafNodeIndex, Member, SignaturePublicKey},
+    treesync::{RatchetTree, RatchetTreeIn},
 };
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "async")]
+use crate::{
+    async_provider::{
+        AsyncMlsAssistProvider, AsyncMlsAssistStorageProvider, AsyncProviderError,
+        AsyncStorageError, StagingStorage,
+    },
+    memory_provider::Codec,
+};
+use crate::{
+    provider_traits::{Clock, MlsAssistProvider, MlsAssistStorageProvider},
+    tls_codec::{Deserialize as _, Serialize as _},
+};
+
+use super::{
+    PAST_GROUP_STATE_REQUEST_VALIDITY,
+    errors::StorageError,
+    tree_chunks::{ChunkHash, ChunkStore},
+};
+
+#[cfg(doc)]
+use crate::messages::PastGroupStateRequest;
+
+/// Limits for the retention of past group states. Past group states are
+/// removed once they are older than the maximum age. If there are more past
+/// group states than the maximum number of epochs or their total stored size
+/// exceeds the maximum size, the oldest ones are removed as well.
+///
+/// The policy also determines how far the timestamp of a
+/// [`PastGroupStateRequest`] may be from the current time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    max_age: Duration,
+    max_epochs: Option<usize>,
+    max_total_size: Option<usize>,
+    request_validity: Duration,
+}
+
+impl RetentionPolicy {
+    /// Create a new [`RetentionPolicy`] that only limits the age of past group
+    /// states. Requests are valid for
+    /// [`PAST_GROUP_STATE_REQUEST_VALIDITY`] seconds.
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            max_epochs: None,
+            max_total_size: None,
+            request_validity: Duration::seconds(PAST_GROUP_STATE_REQUEST_VALIDITY),
+        }
+    }
+
+    /// Limit the number of epochs for which past group states are retained.
+    pub fn with_max_epochs(mut self, max_epochs: usize) -> Self {
+        self.max_epochs = Some(max_epochs);
+        self
+    }
+
+    /// Limit the total stored size of the retained past group states in bytes.
+    /// Since past group states share the parts of their trees that are equal,
+    /// this can be less than the sum of the sizes of their trees.
+    pub fn with_max_total_size(mut self, max_total_size: usize) -> Self {
+        self.max_total_size = Some(max_total_size);
+        self
+    }
+
+    /// Set the maximum difference between the timestamp of a
+    /// [`PastGroupStateRequest`] and the current time. Within this window, a
+    /// request can be replayed as long as the group stays in the same epoch.
+    pub fn with_request_validity(mut self, request_validity: Duration) -> Self {
+        self.request_validity = request_validity;
+        self
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    pub fn max_epochs(&self) -> Option<usize> {
+        self.max_epochs
+    }
+
+    pub fn max_total_size(&self) -> Option<usize> {
+        self.max_total_size
+    }
+
+    pub fn request_validity(&self) -> Duration {
+        self.request_validity
+    }
+}
+
+/// Statistics on the past group states removed when enforcing a
+/// [`RetentionPolicy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvictionStats {
+    /// Number of past group states removed because they expired.
+    pub expired: usize,
+    /// Number of past group states removed because the maximum number of
+    /// epochs was exceeded.
+    pub over_epoch_limit: usize,
+    /// Number of past group states removed because the maximum total size was
+    /// exceeded.
+    pub over_size_limit: usize,
+    /// Number of bytes freed by removing the past group states. Parts of
+    /// their trees that are shared with retained past group states are not
+    /// freed.
+    pub freed_bytes: usize,
+}
+
+impl AddAssign for EvictionStats {
+    fn add_assign(&mut self, other: Self) {
+        self.expired += other.expired;
+        self.over_epoch_limit += other.over_epoch_limit;
+        self.over_size_limit += other.over_size_limit;
+        self.freed_bytes += other.freed_bytes;
+    }
+}
+
+/// Statistics on a sweep over the past group states of all groups.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SweepStats {
+    /// Number of groups whose past group states were checked.
+    pub groups: usize,
+    /// Number of groups whose past group states were deleted from storage
+    /// because none remained.
+    pub deleted_records: usize,
+    /// The past group states removed across all groups, including the number
+    /// of bytes freed.
+    pub evictions: EvictionStats,
+}
+
+/// Remove past group states according to the given retention policy for all
+/// groups in the provider's storage, including groups that don't see any
+/// traffic. Records that end up empty are deleted.
+pub fn sweep_past_group_states<Provider: MlsAssistProvider>(
+    provider: &Provider,
+    retention_policy: &RetentionPolicy,
+) -> Result<SweepStats, StorageError<Provider::Storage>> {
+    let storage = provider.storage();
+    let now = provider.clock().now();
+    let mut stats = SweepStats::default();
+    for group_id in storage.group_ids::<GroupId>()? {
+        sweep_group(storage, &group_id, retention_policy, now, &mut stats)?;
+    }
+    Ok(stats)
+}
+
+/// Async version of [`sweep_past_group_states`]. The records of each group
+/// are read and written in one step each.
+#[cfg(feature = "async")]
+pub async fn sweep_past_group_states_async<Provider: AsyncMlsAssistProvider>(
+    provider: &Provider,
+    retention_policy: &RetentionPolicy,
+) -> Result<SweepStats, AsyncProviderError<Provider>> {
+    let storage = provider.storage();
+    let now = provider.clock().now();
+    let mut stats = SweepStats::default();
+    let group_ids = storage
+        .group_ids()
+        .await
+        .map_err(AsyncStorageError::Storage)?;
+    for group_id_bytes in group_ids {
+        let group_id: GroupId =
+            Provider::Codec::from_slice(&group_id_bytes).map_err(AsyncStorageError::Codec)?;
+        let records = storage
+            .read_group(&group_id_bytes)
+            .await
+            .map_err(AsyncStorageError::Storage)?;
+        let staging_storage = StagingStorage::<Provider::Codec, _>::new(&group_id_bytes, records);
+        sweep_group(
+            &staging_storage,
+            &group_id,
+            retention_policy,
+            now,
+            &mut stats,
+        )?;
+        let changes = staging_storage.take_changes(&group_id_bytes);
+        if !changes.is_empty() {
+            storage
+                .write_group(&group_id_bytes, changes)
+                .await
+                .map_err(AsyncStorageError::Storage)?;
+        }
+    }
+    Ok(stats)
+}
+
+/// Enforce the retention policy on the past group states of the given group
+/// and add the outcome to `stats`. The records of the group are updated in
+/// one transaction, so that a failure doesn't leave some of them behind.
+fn sweep_group<Storage: MlsAssistStorageProvider>(
+    storage: &Storage,
+    group_id: &GroupId,
+    retention_policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+    stats: &mut SweepStats,
+) -> Result<(), StorageError<Storage>> {
+    let sweep = storage.with_transaction(|| -> Result<_, StorageError<Storage>> {
+        let mut past_group_states = PastGroupStates::load(storage, group_id)?;
+        if past_group_states.past_group_states.is_empty() {
+            return Ok(None);
+        }
+        let evictions = past_group_states.enforce_retention_policy(retention_policy, now);
+        past_group_states.write(storage, group_id)?;
+        Ok(Some((
+            evictions,
+            past_group_states.past_group_states.is_empty(),
+        )))
+    })??;
+    if let Some((evictions, deleted_records)) = sweep {
+        stats.groups += 1;
+        if deleted_records {
+            stats.deleted_records += 1;
+        }
+        stats.evictions += evictions;
+    }
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize)]
 struct PastGroupState {
-    nodes: RatchetTree,
+    /// The chunks of the TLS-serialized tree of this group state.
+    chunks: Vec<ChunkHash>,
     creation_time: DateTime<Utc>,
     potential_joiners: HashSet<SignaturePublicKey>,
+    /// The refs of the key packages with which the potential joiners were
+    /// added and their signature keys. Stored as pairs rather than as a map,
+    /// since codecs like JSON only support maps with string keys.
+    #[serde(default)]
+    key_package_refs: Vec<(KeyPackageRef, SignaturePublicKey)>,
+    /// The leaves the potential joiners were added at and their signature
+    /// keys. Empty for states stored before the leaves were recorded.
+    #[serde(default)]
+    leaves: Vec<(LeafNodeIndex, SignaturePublicKey)>,
 }
 
 impl PastGroupState {
-    /// Create a new [`PastGroupState`] with the creation time set to now.
-    fn new(nodes: RatchetTree, potential_joiners: &[SignaturePublicKey]) -> Self {
-        let mut potential_joiners_set = HashSet::with_capacity(potential_joiners.len());
-        for joiner in potential_joiners {
-            potential_joiners_set.insert(joiner.clone());
-        }
+    /// Create a new [`PastGroupState`] with the given creation time.
+    fn new(
+        chunks: Vec<ChunkHash>,
+        potential_joiners: &[(KeyPackageRef, SignaturePublicKey)],
+        joiner_leaves: &HashMap<KeyPackageRef, LeafNodeIndex>,
+        creation_time: DateTime<Utc>,
+    ) -> Self {
         Self {
-            nodes,
-            creation_time: Utc::now(),
-            potential_joiners: potential_joiners_set,
+            chunks,
+            creation_time,
+            potential_joiners: potential_joiners
+                .iter()
+                .map(|(_, joiner)| joiner.clone())
+                .collect(),
+            key_package_refs: potential_joiners.to_vec(),
+            leaves: potential_joiners
+                .iter()
+                .filter_map(|(key_package_ref, joiner)| {
+                    let leaf_index = joiner_leaves.get(key_package_ref)?;
+                    Some((*leaf_index, joiner.clone()))
+                })
+                .collect(),
         }
     }
 
-    /// Get the nodes of this group state.
-    fn nodes(&self) -> &RatchetTree {
-        &self.nodes
+    /// Returns the signature keys of the potential joiners that are among
+    /// the given removed members. Joiners are identified by the leaf they
+    /// were added at, so that they are found even if they changed their
+    /// signature key since. States that don't record the leaves fall back to
+    /// the removed members' current signature keys.
+    fn removed_joiners(&self, removed_members: &[Member]) -> Vec<SignaturePublicKey> {
+        if self.leaves.is_empty() {
+            return removed_members
+                .iter()
+                .map(|member| SignaturePublicKey::from(member.signature_key.clone()))
+                .filter(|signature_key| self.potential_joiners.contains(signature_key))
+                .collect();
+        }
+        self.leaves
+            .iter()
+            .filter(|(leaf_index, _)| {
+                removed_members
+                    .iter()
+                    .any(|member| member.index == *leaf_index)
+            })
+            .map(|(_, joiner)| joiner.clone())
+            .collect()
     }
 
     /// Returns true if the given joiner is authorized to obtain this group state.
@@ -43,67 +305,441 @@ impl PastGroupState {
     }
 
     /// Returns true if the creation of this group state is at least
-    /// `expiration_time` seconds ago.
-    fn has_expired(&self, expiration_time: Duration) -> bool {
-        Utc::now() - expiration_time >= self.creation_time
+    /// `expiration_time` before `now`.
+    fn has_expired(&self, expiration_time: Duration, now: DateTime<Utc>) -> bool {
+        now - expiration_time >= self.creation_time
     }
 }
 
-#[derive(Serialize, Deserialize, Default)]
+/// The past group states of a group as they were stored before each past
+/// group state became a separate record, with the full tree of each epoch.
+#[derive(Deserialize)]
+pub(crate) struct LegacyPastGroupStates {
+    past_group_states: HashMap<GroupEpoch, LegacyPastGroupState>,
+}
+
+#[derive(Deserialize)]
+struct LegacyPastGroupState {
+    nodes: RatchetTree,
+    creation_time: DateTime<Utc>,
+    potential_joiners: HashSet<SignaturePublicKey>,
+}
+
+impl LegacyPastGroupStates {
+    /// Write these past group states of the given group to storage as
+    /// separate records. The refs of the key packages the joiners were added
+    /// with weren't recorded, so the joiners can only be identified by their
+    /// signature keys.
+    pub(crate) fn migrate<Storage: MlsAssistStorageProvider>(
+        self,
+        storage: &Storage,
+        group_id: &GroupId,
+    ) -> Result<(), StorageError<Storage>> {
+        let mut past_group_states = PastGroupStates::default();
+        for (epoch, legacy_state) in self.past_group_states {
+            // A tree that can't be serialized couldn't be sent to the joiners
+            // either.
+            let Ok(tree_bytes) = legacy_state.nodes.tls_serialize_detached() else {
+                continue;
+            };
+            let past_group_state = PastGroupState {
+                chunks: past_group_states.chunks.insert(&tree_bytes),
+                creation_time: legacy_state.creation_time,
+                potential_joiners: legacy_state.potential_joiners,
+                key_package_refs: Vec::new(),
+                leaves: Vec::new(),
+            };
+            past_group_states
+                .past_group_states
+                .insert(epoch, past_group_state);
+            past_group_states.changed_epochs.insert(epoch);
+        }
+        past_group_states.write(storage, group_id)
+    }
+}
+
+/// The past group states of a group. The trees of all past group states are
+/// stored in a shared [`ChunkStore`], so that the parts they have in common
+/// are only stored once.
+///
+/// Each past group state and each chunk is stored as a separate record. Only
+/// the records that changed are written to storage, so that the cost of
+/// accepting a message doesn't grow with the number of retained past group
+/// states.
+#[derive(Default)]
 pub(super) struct PastGroupStates {
     past_group_states: HashMap<GroupEpoch, PastGroupState>,
+    chunks: ChunkStore,
+    /// Epochs whose past group states were added, changed or removed since
+    /// they were last written to storage.
+    changed_epochs: HashSet<GroupEpoch>,
 }
 
-// TODO: With every processing action, check for expired time stamps
-// TODO: Remove the removal logic upon removal of a group member
-
 impl PastGroupStates {
+    /// Load the past group states of the given group and the chunks of their
+    /// trees from storage.
+    pub(super) fn load<Storage: MlsAssistStorageProvider>(
+        storage: &Storage,
+        group_id: &GroupId,
+    ) -> Result<Self, StorageError<Storage>> {
+        let mut past_group_states = Self::default();
+        for epoch in storage.past_group_state_epochs::<GroupEpoch>(group_id)? {
+            let Some(past_group_state) =
+                storage.read_past_group_state::<PastGroupState>(group_id, &epoch)?
+            else {
+                continue;
+            };
+            for chunk_hash in &past_group_state.chunks {
+                past_group_states
+                    .chunks
+                    .add_reference(*chunk_hash, |chunk_hash| {
+                        storage.read_past_group_state_chunk(group_id, chunk_hash)
+                    })?;
+            }
+            past_group_states
+                .past_group_states
+                .insert(epoch, past_group_state);
+        }
+        Ok(past_group_states)
+    }
+
+    /// Write the past group states and chunks that changed since they were
+    /// last loaded or written to storage.
+    pub(super) fn write<Storage: MlsAssistStorageProvider>(
+        &mut self,
+        storage: &Storage,
+        group_id: &GroupId,
+    ) -> Result<(), StorageError<Storage>> {
+        // Write new chunks before the past group states referring to them and
+        // delete removed chunks after the past group states referring to
+        // them, so that stored past group states are never missing chunks.
+        let mut removed_chunks = Vec::new();
+        for (chunk_hash, bytes) in self.chunks.take_changes() {
+            match bytes {
+                Some(bytes) => {
+                    storage.write_past_group_state_chunk(group_id, &chunk_hash, &bytes)?
+                }
+                None => removed_chunks.push(chunk_hash),
+            }
+        }
+        for epoch in std::mem::take(&mut self.changed_epochs) {
+            match self.past_group_states.get(&epoch) {
+                Some(past_group_state) => {
+                    storage.write_past_group_state(group_id, &epoch, past_group_state)?
+                }
+                None => storage.delete_past_group_state(group_id, &epoch)?,
+            }
+        }
+        for chunk_hash in removed_chunks {
+            storage.delete_past_group_state_chunk(group_id, &chunk_hash)?;
+        }
+        Ok(())
+    }
+
     /// Add a new group state with the given nodes for the given epoch
-    /// retrievable by any of the `potential_joiners`.
+    /// retrievable by any of the `potential_joiners`, which are given as the
+    /// refs of the key packages they were added with and their signature keys.
+    /// `joiner_leaves` are the leaves the joiners were added at, indexed by
+    /// the refs of their key packages. `now` is the creation time of the group
+    /// state.
     pub(super) fn add_state(
         &mut self,
         epoch: GroupEpoch,
         nodes: RatchetTree,
-        potential_joiners: &[SignaturePublicKey],
+        potential_joiners: &[(KeyPackageRef, SignaturePublicKey)],
+        joiner_leaves: &HashMap<KeyPackageRef, LeafNodeIndex>,
+        now: DateTime<Utc>,
     ) {
         if potential_joiners.is_empty() {
             return;
         }
-        self.past_group_states
-            .insert(epoch, PastGroupState::new(nodes, potential_joiners));
+        // A tree that can't be serialized couldn't be sent to the joiners
+        // either.
+        let Ok(tree_bytes) = nodes.tls_serialize_detached() else {
+            return;
+        };
+        let chunks = self.chunks.insert(&tree_bytes);
+        let past_group_state = PastGroupState::new(chunks, potential_joiners, joiner_leaves, now);
+        if let Some(replaced_state) = self.past_group_states.insert(epoch, past_group_state) {
+            self.chunks.release(&replaced_state.chunks);
+        }
+        self.changed_epochs.insert(epoch);
     }
 
     /// Get the nodes of the past group state with the given epoch for the given
     /// joiner. Returns `None` if there is no past group state for that epoch
-    /// and the given joiner.
+    /// and the given joiner or if it expired according to `expiration_time`
+    /// and `now`.
     pub(crate) fn get_for_joiner(
         &self,
         epoch: &GroupEpoch,
         joiner: &SignaturePublicKey,
-    ) -> Option<&RatchetTree> {
+        expiration_time: Duration,
+        now: DateTime<Utc>,
+    ) -> Option<RatchetTreeIn> {
+        let tree_bytes = self.tree_bytes_for_joiner(epoch, joiner, expiration_time, now)?;
+        RatchetTreeIn::tls_deserialize_exact(&tree_bytes).ok()
+    }
+
+    /// Like [`Self::get_for_joiner`], but returns the TLS-serialized nodes as
+    /// they are encoded in the `ratchet_tree` extension. The chunks of a tree
+    /// are parts of its TLS encoding, so they only have to be concatenated.
+    pub(crate) fn tree_bytes_for_joiner(
+        &self,
+        epoch: &GroupEpoch,
+        joiner: &SignaturePublicKey,
+        expiration_time: Duration,
+        now: DateTime<Utc>,
+    ) -> Option<Vec<u8>> {
+        let past_group_state = self.past_group_states.get(epoch)?;
+        // Check if the joiner is authorized to get these nodes.
+        if !past_group_state.is_authorized(joiner) {
+            return None;
+        }
+        // Expired states might not have been removed yet.
+        if past_group_state.has_expired(expiration_time, now) {
+            return None;
+        }
+        self.chunks.assemble(&past_group_state.chunks)
+    }
+
+    /// Returns the signature key of the joiner that was added to the group
+    /// with the given key package in the given epoch.
+    pub(super) fn joiner_signature_key(
+        &self,
+        epoch: &GroupEpoch,
+        key_package_ref: &KeyPackageRef,
+    ) -> Option<&SignaturePublicKey> {
         self.past_group_states
-            .get(epoch)
-            .and_then(|past_group_state| {
-                // Check if the joiner is authorized to get these nodes.
-                if past_group_state.is_authorized(joiner) {
-                    Some(past_group_state.nodes())
-                } else {
-                    None
-                }
-            })
+            .get(epoch)?
+            .key_package_refs
+            .iter()
+            .find(|(candidate, _)| candidate == key_package_ref)
+            .map(|(_, joiner)| joiner)
     }
 
-    /// Remove all past group states where the time of creation was longer than
-    /// `expiration_time` in seconds ago.
-    pub(super) fn remove_expired_states(&mut self, expiration_time: Duration) {
-        let mut expired_epochs = vec![];
-        for (epoch, past_group_state) in self.past_group_states.iter() {
-            if past_group_state.has_expired(expiration_time) {
-                expired_epochs.push(*epoch)
+    /// Revoke the authorization of the given members to obtain any past group
+    /// state they were added in, because they were removed from the group.
+    /// Past group states without remaining potential joiners are removed.
+    pub(super) fn remove_joiners(&mut self, removed_members: &[Member]) {
+        if removed_members.is_empty() {
+            return;
+        }
+        let chunks = &mut self.chunks;
+        let changed_epochs = &mut self.changed_epochs;
+        self.past_group_states.retain(|epoch, past_group_state| {
+            let removed_joiners = past_group_state.removed_joiners(removed_members);
+            if removed_joiners.is_empty() {
+                return true;
+            }
+            changed_epochs.insert(*epoch);
+            past_group_state
+                .potential_joiners
+                .retain(|joiner| !removed_joiners.contains(joiner));
+            past_group_state
+                .key_package_refs
+                .retain(|(_, joiner)| !removed_joiners.contains(joiner));
+            past_group_state
+                .leaves
+                .retain(|(_, joiner)| !removed_joiners.contains(joiner));
+            if past_group_state.potential_joiners.is_empty() {
+                chunks.release(&past_group_state.chunks);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Remove past group states according to the given retention policy.
+    /// Expired states are removed first, where `now` is the time expiration is
+    /// checked against. If the remaining states exceed the policy's limits,
+    /// the oldest ones are removed until they don't.
+    pub(super) fn enforce_retention_policy(
+        &mut self,
+        retention_policy: &RetentionPolicy,
+        now: DateTime<Utc>,
+    ) -> EvictionStats {
+        let mut stats = EvictionStats::default();
+        let mut epochs = self.past_group_states.keys().copied().collect::<Vec<_>>();
+        epochs.sort_by_key(|epoch| epoch.as_u64());
+        let mut retained_epochs = VecDeque::with_capacity(epochs.len());
+        for epoch in epochs {
+            if self.past_group_states[&epoch].has_expired(retention_policy.max_age(), now) {
+                stats.freed_bytes += self.remove_state(&epoch);
+                stats.expired += 1;
+            } else {
+                retained_epochs.push_back(epoch);
+            }
+        }
+        if let Some(max_epochs) = retention_policy.max_epochs() {
+            while retained_epochs.len() > max_epochs {
+                let Some(epoch) = retained_epochs.pop_front() else {
+                    break;
+                };
+                stats.freed_bytes += self.remove_state(&epoch);
+                stats.over_epoch_limit += 1;
             }
         }
-        for expired_epoch in expired_epochs {
-            self.past_group_states.remove(&expired_epoch);
+        if let Some(max_total_size) = retention_policy.max_total_size() {
+            while self.chunks.total_size() > max_total_size {
+                let Some(epoch) = retained_epochs.pop_front() else {
+                    break;
+                };
+                stats.freed_bytes += self.remove_state(&epoch);
+                stats.over_size_limit += 1;
+            }
+        }
+        stats
+    }
+
+    /// Remove the past group state of the given epoch. Returns the number of
+    /// bytes freed.
+    fn remove_state(&mut self, epoch: &GroupEpoch) -> usize {
+        self.changed_epochs.insert(*epoch);
+        self.past_group_states
+            .remove(epoch)
+            .map(|past_group_state| self.chunks.release(&past_group_state.chunks))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openmls::prelude::Ciphersuite;
+    use openmls_rust_crypto::RustCrypto;
+
+    use crate::test_utils::{Client, TestGroup};
+
+    use super::*;
+
+    fn key_package_ref(seed: u8) -> KeyPackageRef {
+        KeyPackageRef::new(
+            &[seed; 32],
+            Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519,
+            &RustCrypto::default(),
+            b"MLS 1.0 KeyPackage Reference",
+        )
+        .unwrap()
+    }
+
+    /// Returns past group states for the epochs 1, 2, ... created at the
+    /// given times. A member is added in each epoch, so that each tree is
+    /// larger than the previous one.
+    fn new_past_group_states(creation_times: &[DateTime<Utc>]) -> PastGroupStates {
+        let mut test_group = TestGroup::new();
+        let mut past_group_states = PastGroupStates::default();
+        for (epoch, creation_time) in (1..).zip(creation_times) {
+            let joiner = Client::new(&format!("joiner {epoch}"));
+            test_group.add_members(&[joiner.key_package()]);
+            past_group_states.add_state(
+                GroupEpoch::from(epoch),
+                test_group.member("creator").mls_group.export_ratchet_tree(),
+                &[(key_package_ref(epoch as u8), joiner.signature_key())],
+                &HashMap::new(),
+                *creation_time,
+            );
+        }
+        past_group_states
+    }
+
+    fn retained_epochs(past_group_states: &PastGroupStates) -> Vec<u64> {
+        let mut epochs = past_group_states
+            .past_group_states
+            .keys()
+            .map(GroupEpoch::as_u64)
+            .collect::<Vec<_>>();
+        epochs.sort();
+        epochs
+    }
+
+    #[test]
+    fn the_oldest_epochs_are_evicted_beyond_the_epoch_limit() {
+        let now = Utc::now();
+        let mut past_group_states = new_past_group_states(&[now; 4]);
+        let total_size = past_group_states.chunks.total_size();
+        let retention_policy = RetentionPolicy::new(Duration::days(1)).with_max_epochs(2);
+
+        let stats = past_group_states.enforce_retention_policy(&retention_policy, now);
+
+        assert_eq!(retained_epochs(&past_group_states), [3, 4]);
+        assert_eq!(
+            stats,
+            EvictionStats {
+                expired: 0,
+                over_epoch_limit: 2,
+                over_size_limit: 0,
+                freed_bytes: total_size - past_group_states.chunks.total_size(),
+            }
+        );
+        assert!(stats.freed_bytes > 0);
+    }
+
+    #[test]
+    fn the_oldest_epochs_are_evicted_beyond_the_size_limit() {
+        let now = Utc::now();
+        let mut past_group_states = new_past_group_states(&[now; 4]);
+        let total_size = past_group_states.chunks.total_size();
+        // Only the largest tree, the one of the newest epoch, fits.
+        let mut newest_state = new_past_group_states(&[now; 4]);
+        for epoch in 1..=3 {
+            newest_state.remove_state(&GroupEpoch::from(epoch));
         }
+        let retention_policy = RetentionPolicy::new(Duration::days(1))
+            .with_max_total_size(newest_state.chunks.total_size());
+
+        let stats = past_group_states.enforce_retention_policy(&retention_policy, now);
+
+        assert_eq!(retained_epochs(&past_group_states), [4]);
+        assert_eq!(
+            stats,
+            EvictionStats {
+                expired: 0,
+                over_epoch_limit: 0,
+                over_size_limit: 3,
+                freed_bytes: total_size - past_group_states.chunks.total_size(),
+            }
+        );
+    }
+
+    #[test]
+    fn expired_epochs_are_evicted_before_the_limits_are_enforced() {
+        let now = Utc::now();
+        let mut past_group_states = new_past_group_states(&[
+            now - Duration::days(2),
+            now - Duration::hours(3),
+            now - Duration::hours(2),
+            now - Duration::hours(1),
+        ]);
+        let retention_policy = RetentionPolicy::new(Duration::days(1)).with_max_epochs(2);
+
+        let stats = past_group_states.enforce_retention_policy(&retention_policy, now);
+
+        assert_eq!(retained_epochs(&past_group_states), [3, 4]);
+        assert_eq!(stats.expired, 1);
+        assert_eq!(stats.over_epoch_limit, 1);
+        assert_eq!(stats.over_size_limit, 0);
+    }
+
+    #[test]
+    fn past_group_state_round_trips_through_json() {
+        let joiner = SignaturePublicKey::from(vec![1; 32]);
+        let key_package_ref = key_package_ref(2);
+        let past_group_state = PastGroupState::new(
+            vec![],
+            &[(key_package_ref.clone(), joiner.clone())],
+            &HashMap::from([(key_package_ref, LeafNodeIndex::new(1))]),
+            Utc::now(),
+        );
+
+        let serialized = serde_json::to_vec(&past_group_state).unwrap();
+        let deserialized: PastGroupState = serde_json::from_slice(&serialized).unwrap();
+
+        assert!(deserialized.is_authorized(&joiner));
+        assert_eq!(
+            deserialized.key_package_refs,
+            past_group_state.key_package_refs
+        );
+        assert_eq!(deserialized.leaves, past_group_state.leaves);
     }
 }
@@ -0,0 +1,88 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Application-specific rules that are enforced when processing messages.
+
+use openmls::{
+    prelude::{GroupContext, QueuedProposal, StagedCommit},
+    treesync::LeafNode,
+};
+use thiserror::Error;
+
+use super::admin_roles::AdminRolesExtension;
+
+#[cfg(doc)]
+use super::Group;
+
+/// Reason for the rejection of a message by an [`AssistPolicy`].
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum PolicyViolation {
+    /// The sender is not allowed to send this message.
+    #[error("The sender is not allowed to send this message.")]
+    Unauthorized,
+    /// The message would make the group exceed its maximum size.
+    #[error("The message would make the group exceed its maximum size.")]
+    GroupTooLarge,
+    /// The message contains a proposal of a type that is not allowed.
+    #[error("The message contains a proposal of a type that is not allowed.")]
+    ProposalTypeNotAllowed,
+    /// Application-specific reason.
+    #[error("Policy violation: {0}")]
+    Other(String),
+}
+
+/// A policy that is evaluated by [`Group::process_assisted_message_with_policy`]
+/// for every commit and proposal that is processed. The commit and proposal
+/// checks accept every message by default.
+///
+/// The sender's leaf is the leaf of the sending member, the leaf a new member
+/// joins with, or `None` for external senders. The group context is the one
+/// of the group's current epoch.
+pub trait AssistPolicy {
+    /// Check a commit before it is returned for acceptance.
+    fn check_commit(
+        &self,
+        _staged_commit: &StagedCommit,
+        _sender_leaf: Option<&LeafNode>,
+        _group_context: &GroupContext,
+    ) -> Result<(), PolicyViolation> {
+        Ok(())
+    }
+
+    /// Check a proposal before it is returned for acceptance.
+    fn check_proposal(
+        &self,
+        _proposal: &QueuedProposal,
+        _sender_leaf: Option<&LeafNode>,
+        _group_context: &GroupContext,
+    ) -> Result<(), PolicyViolation> {
+        Ok(())
+    }
+
+    /// Check a GroupContextExtensions proposal that introduces admin roles
+    /// into a group that has none yet. The proposer's leaf is `None` for
+    /// external senders. Refused by default, so that members can't make
+    /// themselves admins of a group without admin roles.
+    fn check_admin_roles_introduction(
+        &self,
+        _admin_roles: &AdminRolesExtension,
+        _proposer_leaf: Option<&LeafNode>,
+        _group_context: &GroupContext,
+    ) -> Result<(), PolicyViolation> {
+        Err(PolicyViolation::Unauthorized)
+    }
+}
+
+/// The empty policy accepts every message, including ones that introduce
+/// admin roles.
+impl AssistPolicy for () {
+    fn check_admin_roles_introduction(
+        &self,
+        _admin_roles: &AdminRolesExtension,
+        _proposer_leaf: Option<&LeafNode>,
+        _group_context: &GroupContext,
+    ) -> Result<(), PolicyViolation> {
+        Ok(())
+    }
+}
@@ -4,15 +4,39 @@
 
 use openmls::prelude::{ContentType, OpenMlsCrypto, ProtocolMessage, Verifiable};
 
-use super::{errors::LibraryError, *};
+use super::{errors::LibraryError, policy::AssistPolicy, *};
 
 impl Group {
     /// Returns a [`ProcessedMessage`] for inspection.
+    ///
+    /// Commits and proposals that were already accepted are returned as
+    /// [`ProcessedAssistedMessage::Duplicate`] without processing them again.
+    /// Proposals with the same content and sender as a proposal in the
+    /// proposal store are returned as duplicates of the current epoch.
     pub fn process_assisted_message<CryptoProvider: OpenMlsCrypto>(
         &self,
         provider: &CryptoProvider,
         assisted_message: AssistedMessageIn,
     ) -> Result<ProcessedAssistedMessagePlus, ProcessAssistedMessageError> {
+        self.process_assisted_message_with_policy(provider, assisted_message, &())
+    }
+
+    /// Same as [`Group::process_assisted_message`], but additionally checks
+    /// every commit and proposal against the given [`AssistPolicy`].
+    pub fn process_assisted_message_with_policy<CryptoProvider: OpenMlsCrypto>(
+        &self,
+        provider: &CryptoProvider,
+        assisted_message: AssistedMessageIn,
+        policy: &impl AssistPolicy,
+    ) -> Result<ProcessedAssistedMessagePlus, ProcessAssistedMessageError> {
+        if let Some(epoch) = self.duplicate_epoch(&assisted_message) {
+            let message_plus = ProcessedAssistedMessagePlus {
+                processed_assisted_message: ProcessedAssistedMessage::Duplicate(epoch),
+                serialized_mls_message: assisted_message.serialized_mls_message,
+            };
+            return Ok(message_plus);
+        }
+        self.check_commit_conflict(&assisted_message.mls_message)?;
         let (commit, assisted_group_info) = match assisted_message.mls_message {
             ProtocolMessage::PrivateMessage(private_message) => {
                 // We can't process private messages using the PublicGroup, so
@@ -35,9 +59,53 @@ impl Group {
                         // Proposals are fed to the PublicGroup s.t. they are
                         // put into the ProposalStore. Otherwise we don't do
                         // anything with them.
+                        if self.accepted_messages.proposal_limit_reached(self.epoch()) {
+                            return Err(ProcessAssistedMessageError::TooManyProposals);
+                        }
                         let processed_message = self.public_group.process_message(provider, *pm)?;
+                        // Proposals by new members may only be Add proposals
+                        // for the sender itself.
+                        if let ProcessedMessageContent::ExternalJoinProposalMessage(queued_proposal) =
+                            processed_message.content()
+                            && !matches!(queued_proposal.proposal(), Proposal::Add(_))
+                        {
+                            return Err(ProcessAssistedMessageError::InvalidAssistedMessage);
+                        }
+                        let (ProcessedMessageContent::ProposalMessage(queued_proposal)
+                        | ProcessedMessageContent::ExternalJoinProposalMessage(queued_proposal)) =
+                            processed_message.content()
+                        else {
+                            return Err(ProcessAssistedMessageError::LibraryError(
+                                LibraryError::LibraryError, // Mismatching message type
+                            ));
+                        };
+                        // Proposals that are already queued may have been
+                        // resubmitted with a different encoding.
+                        if self.is_queued(queued_proposal) {
+                            let message_plus = ProcessedAssistedMessagePlus {
+                                processed_assisted_message: ProcessedAssistedMessage::Duplicate(
+                                    self.epoch(),
+                                ),
+                                serialized_mls_message: assisted_message.serialized_mls_message,
+                            };
+                            return Ok(message_plus);
+                        }
+                        policy.check_proposal(
+                            queued_proposal,
+                            self.sender_leaf(&processed_message),
+                            self.public_group.group_context(),
+                        )?;
                         let processed_assisted_message =
-                            ProcessedAssistedMessage::NonCommit(processed_message);
+                            if let Sender::External(sender_index) = processed_message.sender() {
+                                let external_sender = self
+                                    .validate_external_proposal(sender_index, &processed_message)?;
+                                ProcessedAssistedMessage::ExternalProposal(
+                                    processed_message,
+                                    external_sender,
+                                )
+                            } else {
+                                ProcessedAssistedMessage::NonCommit(processed_message)
+                            };
                         let message_plus = ProcessedAssistedMessagePlus {
                             processed_assisted_message,
                             serialized_mls_message: assisted_message.serialized_mls_message,
@@ -78,8 +146,8 @@ impl Group {
             Sender::Member(leaf_index) => AssistedSender::Member(leaf_index),
             Sender::NewMemberCommit => {
                 // If it's a new member commit, we can figure out the signature
-                // key of the sender by looking at the add proposal.
-                let Some(external_add) = staged_commit.update_path_leaf_node() else {
+                // key of the sender by looking at the update path.
+                let Some(external_add) = self.sender_leaf(&processed_message) else {
                     return Err(ProcessAssistedMessageError::UnknownSender);
                 };
                 let signature_key = external_add.signature_key().clone();
@@ -102,6 +170,17 @@ impl Group {
         if group_info.group_context() != staged_commit.group_context() {
             return Err(ProcessAssistedMessageError::InconsistentGroupContext);
         }
+        self.check_admin_rights(
+            staged_commit,
+            &sender,
+            self.sender_leaf(&processed_message),
+            policy,
+        )?;
+        policy.check_commit(
+            staged_commit,
+            self.sender_leaf(&processed_message),
+            self.public_group.group_context(),
+        )?;
         let processed_assisted_message =
             ProcessedAssistedMessage::Commit(processed_message, group_info);
         let message_plus = ProcessedAssistedMessagePlus {
@@ -119,6 +198,96 @@ enum AssistedSender {
 
 // Helper functions
 impl Group {
+    /// Returns the leaf of the sender of the given message. For members, this
+    /// is their leaf in the current tree. For new members, it's the leaf they
+    /// join with. External senders don't have a leaf.
+    fn sender_leaf<'a>(&'a self, processed_message: &'a ProcessedMessage) -> Option<&'a LeafNode> {
+        match (processed_message.sender(), processed_message.content()) {
+            (Sender::Member(leaf_index), _) => self.public_group.leaf(*leaf_index),
+            (
+                Sender::NewMemberCommit,
+                ProcessedMessageContent::StagedCommitMessage(staged_commit),
+            ) => staged_commit.update_path_leaf_node(),
+            (
+                Sender::NewMemberProposal,
+                ProcessedMessageContent::ExternalJoinProposalMessage(queued_proposal),
+            ) => match queued_proposal.proposal() {
+                Proposal::Add(add_proposal) => Some(add_proposal.key_package().leaf_node()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the epoch in which the given message was accepted if it is a
+    /// commit or proposal that was already accepted.
+    fn duplicate_epoch(&self, assisted_message: &AssistedMessageIn) -> Option<GroupEpoch> {
+        if let ProtocolMessage::PrivateMessage(_) = assisted_message.mls_message {
+            return None;
+        }
+        self.accepted_messages
+            .accepted_epoch(&assisted_message.serialized_mls_message)
+    }
+
+    /// Returns true if a proposal with the same content and sender as the
+    /// given one is in the proposal store.
+    fn is_queued(&self, queued_proposal: &QueuedProposal) -> bool {
+        self.queued_proposals.iter().any(|candidate| {
+            candidate.proposal() == queued_proposal.proposal()
+                && candidate.sender() == queued_proposal.sender()
+        })
+    }
+
+    /// Returns a [`ProcessAssistedMessageError::CommitConflict`] if the given
+    /// message is a commit for a past epoch for which another commit was
+    /// already accepted. The first commit accepted for an epoch wins.
+    fn check_commit_conflict(
+        &self,
+        mls_message: &ProtocolMessage,
+    ) -> Result<(), ProcessAssistedMessageError> {
+        if mls_message.content_type() != ContentType::Commit {
+            return Ok(());
+        }
+        let commit_epoch = mls_message.epoch();
+        let current_epoch = self.epoch();
+        if commit_epoch.as_u64() >= current_epoch.as_u64() {
+            return Ok(());
+        }
+        match self.accepted_messages.commit(commit_epoch) {
+            Some(winning_commit) => Err(ProcessAssistedMessageError::CommitConflict {
+                winning_commit: winning_commit.clone(),
+                current_epoch,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that a proposal by an external sender comes from one of the
+    /// senders listed in the group's external senders extension and that it
+    /// is of a type external senders may send. Returns the matching external
+    /// sender.
+    fn validate_external_proposal(
+        &self,
+        sender_index: &SenderExtensionIndex,
+        processed_message: &ProcessedMessage,
+    ) -> Result<ExternalSender, ProcessAssistedMessageError> {
+        let external_sender = self
+            .external_sender(sender_index)
+            .ok_or(ProcessAssistedMessageError::UnknownSender)?;
+        let ProcessedMessageContent::ProposalMessage(queued_proposal) = processed_message.content()
+        else {
+            return Err(ProcessAssistedMessageError::LibraryError(
+                LibraryError::LibraryError, // Mismatching message type
+            ));
+        };
+        match queued_proposal.proposal() {
+            Proposal::Add(_) | Proposal::Remove(_) | Proposal::GroupContextExtensions(_) => {
+                Ok(external_sender.clone())
+            }
+            _ => Err(ProcessAssistedMessageError::InvalidExternalProposal),
+        }
+    }
+
     fn validate_group_info<CryptoProvider: OpenMlsCrypto>(
         &self,
         provider: &CryptoProvider,
@@ -130,15 +299,19 @@ impl Group {
         let signature_scheme = self.group_info().group_context().ciphersuite().into();
         let (sender_index, sender_pk) = match sender {
             AssistedSender::Member(index) => {
-                let sender_pk = self
-                    .public_group
-                    .members()
-                    .find_map(|m| {
-                        if m.index == index {
-                            Some(m.signature_key)
-                        } else {
-                            None
-                        }
+                // If the committer updated their leaf, the group info is
+                // signed with the signature key of the new leaf.
+                let sender_pk = staged_commit
+                    .update_path_leaf_node()
+                    .map(|leaf_node| leaf_node.signature_key().as_slice().to_vec())
+                    .or_else(|| {
+                        self.public_group.members().find_map(|m| {
+                            if m.index == index {
+                                Some(m.signature_key)
+                            } else {
+                                None
+                            }
+                        })
                     })
                     .map(|pk_bytes| {
                         OpenMlsSignaturePublicKey::from_signature_key(
@@ -0,0 +1,137 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use openmls::prelude::ProtocolMessage;
+
+use super::{errors::RecipientsError, *};
+
+/// A group member that should receive a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recipient {
+    pub leaf_index: LeafNodeIndex,
+    pub signature_key: SignaturePublicKey,
+}
+
+impl From<Member> for Recipient {
+    fn from(member: Member) -> Self {
+        Self {
+            leaf_index: member.index,
+            signature_key: member.signature_key.into(),
+        }
+    }
+}
+
+/// The recipients of a processed message.
+#[derive(Debug, Clone, Default)]
+pub struct Recipients {
+    /// Members that should receive the message itself.
+    pub members: Vec<Recipient>,
+    /// Signature keys of the clients added by a commit. They should receive
+    /// the Welcome instead of the commit.
+    pub welcome_recipients: Vec<SignaturePublicKey>,
+}
+
+impl Group {
+    /// Returns the recipients of the given message.
+    ///
+    /// This has to be called before the message is accepted, as commits are
+    /// distributed to the members of the epoch they were sent in. That
+    /// includes members removed by the commit, but not its sender. Members
+    /// that updated their leaf with the commit are listed with their new
+    /// signature key. Clients added by the commit are listed as Welcome
+    /// recipients instead.
+    ///
+    /// Proposals are distributed to all members except their sender.
+    /// [`PrivateMessage`](openmls::prelude::PrivateMessage)s are distributed
+    /// to all members, as their sender is encrypted. Since the group doesn't
+    /// keep track of the members of past epochs, a
+    /// [`RecipientsError::WrongEpoch`] is returned for private messages of
+    /// any other epoch than the current one.
+    pub fn recipients_for(
+        &self,
+        processed_assisted_message: &ProcessedAssistedMessage,
+    ) -> Result<Recipients, RecipientsError> {
+        let recipients = match processed_assisted_message {
+            ProcessedAssistedMessage::PrivateMessage(private_message) => {
+                let message_epoch = ProtocolMessage::from(private_message.clone()).epoch();
+                if message_epoch != self.epoch() {
+                    return Err(RecipientsError::WrongEpoch {
+                        message_epoch,
+                        current_epoch: self.epoch(),
+                    });
+                }
+                Recipients {
+                    members: self.members().map(Recipient::from).collect(),
+                    welcome_recipients: vec![],
+                }
+            }
+            ProcessedAssistedMessage::NonCommit(processed_message)
+            | ProcessedAssistedMessage::ExternalProposal(processed_message, _) => Recipients {
+                members: self.members_except(processed_message.sender()),
+                welcome_recipients: vec![],
+            },
+            ProcessedAssistedMessage::Commit(processed_message, _) => {
+                let ProcessedMessageContent::StagedCommitMessage(staged_commit) =
+                    processed_message.content()
+                else {
+                    return Ok(Recipients {
+                        members: self.members_except(processed_message.sender()),
+                        welcome_recipients: vec![],
+                    });
+                };
+                let updated_signature_keys = staged_commit
+                    .update_proposals()
+                    .filter_map(|update_proposal| match update_proposal.sender() {
+                        Sender::Member(leaf_index) => Some((
+                            *leaf_index,
+                            update_proposal
+                                .update_proposal()
+                                .leaf_node()
+                                .signature_key()
+                                .clone(),
+                        )),
+                        _ => None,
+                    })
+                    .collect::<HashMap<_, _>>();
+                let members = self
+                    .members_except(processed_message.sender())
+                    .into_iter()
+                    .map(|mut recipient| {
+                        if let Some(signature_key) =
+                            updated_signature_keys.get(&recipient.leaf_index)
+                        {
+                            recipient.signature_key = signature_key.clone();
+                        }
+                        recipient
+                    })
+                    .collect();
+                let welcome_recipients = staged_commit
+                    .add_proposals()
+                    .map(|add_proposal| {
+                        add_proposal
+                            .add_proposal()
+                            .key_package()
+                            .leaf_node()
+                            .signature_key()
+                            .clone()
+                    })
+                    .collect();
+                Recipients {
+                    members,
+                    welcome_recipients,
+                }
+            }
+            ProcessedAssistedMessage::Duplicate(_) => Recipients::default(),
+        };
+        Ok(recipients)
+    }
+
+    /// Returns all current members except the given sender if it's a member.
+    fn members_except(&self, sender: &Sender) -> Vec<Recipient> {
+        self.members()
+            .filter(|member| !matches!(sender, Sender::Member(leaf_index) if *leaf_index == member.index))
+            .map(Recipient::from)
+            .collect()
+    }
+}
@@ -0,0 +1,1419 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use openmls::prelude::{
+    BasicCredential, Extension, ExtensionType, Extensions, ExternalProposal, GroupContext,
+    KeyPackageRef, MlsMessageOut, ProcessMessageError, RequiredCapabilitiesExtension,
+    ValidationError,
+};
+use openmls_rust_crypto::OpenMlsRustCrypto;
+
+use crate::{
+    memory_provider::{MlsAssistRustCrypto, TestClock},
+    messages::{JoinerId, PastGroupStateRequest},
+    test_utils::{
+        Client, JsonCodec, TestGroup, assisted, assisted_welcome, external_reinit_proposal,
+        new_member_remove_proposal,
+    },
+    tls_codec::Serialize as _,
+};
+
+use super::{
+    accepted_messages::RETAINED_EPOCHS,
+    admin_roles::{ADMIN_ROLES_EXTENSION_TYPE, AdminRolesExtension},
+    errors::{
+        ExternalProposalError, PastGroupStateRequestError, RecipientsError, WelcomeValidationError,
+    },
+    past_group_states::{EvictionStats, RetentionPolicy, sweep_past_group_states},
+    policy::{AssistPolicy, PolicyViolation},
+    recipients::Recipient,
+    *,
+};
+
+type Provider = MlsAssistRustCrypto<JsonCodec, TestClock>;
+
+fn provider() -> Provider {
+    Provider::new(Default::default(), TestClock::new(Utc::now()))
+}
+
+fn retention_policy() -> RetentionPolicy {
+    RetentionPolicy::new(Duration::days(1))
+}
+
+/// Add `joiner` to the group and accept the commit. Returns the epoch the
+/// joiner was added in.
+fn add_joiner(
+    provider: &Provider,
+    test_group: &mut TestGroup,
+    group: &mut Group,
+    joiner: &Client,
+) -> GroupEpoch {
+    let (commit, _) = test_group.add_members(&[joiner.key_package()]);
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), commit)
+        .unwrap();
+    group
+        .accept_processed_message(provider, processed_message, &retention_policy())
+        .unwrap();
+    group.epoch()
+}
+
+fn request(
+    provider: &Provider,
+    group: &Group,
+    joiner: &Client,
+    joiner_id: JoinerId,
+    epoch: GroupEpoch,
+) -> PastGroupStateRequest {
+    PastGroupStateRequest::new(
+        &joiner.signer,
+        group.group_info().group_context().group_id().clone(),
+        epoch,
+        group.epoch(),
+        joiner_id,
+        provider.clock().now(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn past_group_state_requests_are_bound_to_the_current_epoch() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let joiner = Client::new("joiner");
+    let epoch = add_joiner(&provider, &mut test_group, &mut group, &joiner);
+
+    let request = request(
+        &provider,
+        &group,
+        &joiner,
+        JoinerId::SignatureKey(joiner.signature_key()),
+        epoch,
+    );
+    let tree = group
+        .past_group_state(&provider, &request, &retention_policy())
+        .unwrap();
+    assert!(tree.is_some());
+
+    add_joiner(
+        &provider,
+        &mut test_group,
+        &mut group,
+        &Client::new("other"),
+    );
+    let error = group
+        .past_group_state(&provider, &request, &retention_policy())
+        .unwrap_err();
+    assert_eq!(
+        error,
+        PastGroupStateRequestError::WrongEpoch {
+            current_epoch: group.epoch()
+        }
+    );
+}
+
+#[test]
+fn past_group_state_request_validity_is_configurable() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let joiner = Client::new("joiner");
+    let epoch = add_joiner(&provider, &mut test_group, &mut group, &joiner);
+    let request = request(
+        &provider,
+        &group,
+        &joiner,
+        JoinerId::SignatureKey(joiner.signature_key()),
+        epoch,
+    );
+
+    provider.clock().advance(Duration::seconds(30));
+    let retention_policy = retention_policy().with_request_validity(Duration::seconds(10));
+    let error = group
+        .past_group_state(&provider, &request, &retention_policy)
+        .unwrap_err();
+    assert_eq!(error, PastGroupStateRequestError::StaleRequest);
+
+    let retention_policy = retention_policy.with_request_validity(Duration::minutes(1));
+    let tree = group
+        .past_group_state(&provider, &request, &retention_policy)
+        .unwrap();
+    assert!(tree.is_some());
+}
+
+#[test]
+fn unknown_key_package_refs_are_indistinguishable_from_invalid_signatures() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let joiner = Client::new("joiner");
+    let key_package = joiner.key_package();
+    let key_package_ref = key_package.hash_ref(provider.crypto()).unwrap();
+    let (commit, _) = test_group.add_members(&[key_package]);
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), commit)
+        .unwrap();
+    group
+        .accept_processed_message(&provider, processed_message, &retention_policy())
+        .unwrap();
+    let epoch = group.epoch();
+
+    let joiner_id = JoinerId::KeyPackageRef(key_package_ref);
+    let valid_request = request(&provider, &group, &joiner, joiner_id.clone(), epoch);
+    let tree = group
+        .past_group_state(&provider, &valid_request, &retention_policy())
+        .unwrap();
+    assert!(tree.is_some());
+
+    // A known key package ref with a signature by someone else.
+    let forged_request = request(&provider, &group, &Client::new("other"), joiner_id, epoch);
+    let forged_error = group
+        .past_group_state(&provider, &forged_request, &retention_policy())
+        .unwrap_err();
+
+    // A key package ref that was never added.
+    let other = Client::new("other");
+    let unknown_ref = other.key_package().hash_ref(provider.crypto()).unwrap();
+    let unknown_request = request(
+        &provider,
+        &group,
+        &other,
+        JoinerId::KeyPackageRef(unknown_ref),
+        epoch,
+    );
+    let unknown_error = group
+        .past_group_state(&provider, &unknown_request, &retention_policy())
+        .unwrap_err();
+
+    assert_eq!(forged_error, PastGroupStateRequestError::InvalidSignature);
+    assert_eq!(unknown_error, forged_error);
+}
+
+#[test]
+fn past_trees_are_served_as_tls_bytes_after_loading() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let joiner = Client::new("joiner");
+    let epoch = add_joiner(&provider, &mut test_group, &mut group, &joiner);
+    let tree_bytes = group
+        .export_ratchet_tree()
+        .tls_serialize_detached()
+        .unwrap();
+
+    let group_id = group.group_info().group_context().group_id().clone();
+    let group = Group::load(provider.storage(), &group_id).unwrap().unwrap();
+    let request = request(
+        &provider,
+        &group,
+        &joiner,
+        JoinerId::SignatureKey(joiner.signature_key()),
+        epoch,
+    );
+    let served_bytes = group
+        .past_group_state_bytes(&provider, &request, &retention_policy())
+        .unwrap();
+    assert_eq!(served_bytes, Some(tree_bytes));
+}
+
+#[test]
+fn join_proposals_are_queued_and_committed() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let joiner = Client::new("joiner");
+
+    let proposal = test_group.propose_join(&joiner);
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), proposal)
+        .unwrap();
+    assert_eq!(
+        processed_message
+            .processed_assisted_message
+            .new_member_proposal_signature_key(),
+        Some(&joiner.signature_key())
+    );
+    let epoch = group.epoch();
+    let outcome = group
+        .accept_processed_message(&provider, processed_message, &retention_policy())
+        .unwrap();
+    assert_eq!(outcome.epoch, epoch);
+    assert!(outcome.added_members.is_empty());
+
+    // The creator commits the queued proposal.
+    let (commit, _) = test_group.commit("creator", |builder| builder);
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), commit)
+        .unwrap();
+    let outcome = group
+        .accept_processed_message(&provider, processed_message, &retention_policy())
+        .unwrap();
+    assert_eq!(outcome.added_members.len(), 1);
+    assert_eq!(
+        outcome.added_members[0].signature_key,
+        joiner.signature_key().as_slice()
+    );
+
+    let request = request(
+        &provider,
+        &group,
+        &joiner,
+        JoinerId::SignatureKey(joiner.signature_key()),
+        group.epoch(),
+    );
+    let tree = group
+        .past_group_state(&provider, &request, &retention_policy())
+        .unwrap();
+    assert!(tree.is_some());
+}
+
+#[test]
+fn new_member_proposals_other_than_adds_are_rejected() {
+    let provider = provider();
+    let test_group = TestGroup::new();
+    let group = test_group.assisted_group(&provider);
+    let creator = test_group.member("creator");
+
+    let proposal = new_member_remove_proposal(
+        &Client::new("joiner"),
+        &creator.mls_group,
+        creator.mls_group.own_leaf_index(),
+    );
+    let error = group
+        .process_assisted_message(provider.crypto(), proposal)
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        ProcessAssistedMessageError::ProcessMessageError(ProcessMessageError::ValidationError(
+            ValidationError::NotAnExternalAddProposal
+        ))
+    );
+}
+
+/// Create a group with the member "bob" and a single external sender.
+/// Returns the group, the assisted group and the external sender.
+fn group_with_external_sender(provider: &Provider) -> (TestGroup, Group, Client) {
+    let external_sender = Client::new("external sender");
+    let mut test_group = TestGroup::with_group_context_extensions(Extensions::single(
+        Extension::ExternalSenders(vec![ExternalSender::new(
+            external_sender.signature_key(),
+            external_sender.credential().clone(),
+        )]),
+    ));
+    let bob = Client::new("bob");
+    let (_, welcome) = test_group.add_members(&[bob.key_package()]);
+    test_group.join(bob, welcome);
+    let group = test_group.assisted_group(provider);
+    (test_group, group, external_sender)
+}
+
+#[test]
+fn proposals_by_external_senders_are_returned_with_the_sender() {
+    let provider = provider();
+    let (test_group, group, external_sender) = group_with_external_sender(&provider);
+    let bob = test_group.member("bob");
+
+    let proposal = ExternalProposal::new_remove::<OpenMlsRustCrypto>(
+        bob.mls_group.own_leaf_index(),
+        bob.mls_group.group_id().clone(),
+        bob.mls_group.epoch(),
+        &external_sender.signer,
+        SenderExtensionIndex::new(0),
+    )
+    .unwrap();
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), assisted(proposal, None))
+        .unwrap();
+    let ProcessedAssistedMessage::ExternalProposal(_, sender) =
+        processed_message.processed_assisted_message
+    else {
+        panic!("Expected an external proposal.");
+    };
+    assert_eq!(
+        sender,
+        ExternalSender::new(
+            external_sender.signature_key(),
+            external_sender.credential().clone(),
+        )
+    );
+}
+
+#[test]
+fn proposals_by_unknown_external_senders_are_rejected() {
+    let provider = provider();
+    let (test_group, group, external_sender) = group_with_external_sender(&provider);
+    let bob = test_group.member("bob");
+
+    let proposal = ExternalProposal::new_remove::<OpenMlsRustCrypto>(
+        bob.mls_group.own_leaf_index(),
+        bob.mls_group.group_id().clone(),
+        bob.mls_group.epoch(),
+        &external_sender.signer,
+        SenderExtensionIndex::new(1),
+    )
+    .unwrap();
+    let error = group
+        .process_assisted_message(provider.crypto(), assisted(proposal, None))
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        ProcessAssistedMessageError::ProcessMessageError(ProcessMessageError::ValidationError(
+            ValidationError::UnauthorizedExternalSender
+        ))
+    );
+}
+
+#[test]
+fn external_proposals_of_disallowed_types_are_rejected() {
+    let provider = provider();
+    let (test_group, group, external_sender) = group_with_external_sender(&provider);
+
+    let proposal =
+        external_reinit_proposal(&external_sender, &test_group.member("bob").mls_group, 0);
+    let error = group
+        .process_assisted_message(provider.crypto(), proposal)
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        ProcessAssistedMessageError::ProcessMessageError(
+            ProcessMessageError::UnsupportedProposalType
+        )
+    );
+}
+
+#[test]
+fn external_proposals_are_accepted_like_proposals_by_clients() {
+    let provider = provider();
+    let (mut test_group, mut group, external_sender) = group_with_external_sender(&provider);
+    let sender = ExternalSender::new(
+        external_sender.signature_key(),
+        external_sender.credential().clone(),
+    );
+
+    let proposal = group
+        .propose_external_remove::<_, OpenMlsRustCrypto>(
+            &provider,
+            &external_sender.signer,
+            &sender,
+            test_group.leaf_index("bob"),
+            &retention_policy(),
+        )
+        .unwrap();
+    // The proposal is recorded as accepted in the current epoch.
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), assisted(proposal.clone(), None))
+        .unwrap();
+    assert!(matches!(
+        processed_message.processed_assisted_message,
+        ProcessedAssistedMessage::Duplicate(epoch) if epoch == group.epoch()
+    ));
+
+    // The proposal can be covered by a commit of a member.
+    test_group.distribute(&external_sender.name, proposal);
+    let (commit, _) = test_group.commit("creator", |builder| builder);
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), commit)
+        .unwrap();
+    let outcome = group
+        .accept_processed_message(&provider, processed_message, &retention_policy())
+        .unwrap();
+    assert_eq!(outcome.removed_members.len(), 1);
+    assert_eq!(group.members().count(), 1);
+}
+
+#[test]
+fn external_adds_are_queued() {
+    let provider = provider();
+    let (mut test_group, mut group, external_sender) = group_with_external_sender(&provider);
+    let sender = ExternalSender::new(
+        external_sender.signature_key(),
+        external_sender.credential().clone(),
+    );
+    let joiner = Client::new("joiner");
+
+    let proposal = group
+        .propose_external_add::<_, OpenMlsRustCrypto>(
+            &provider,
+            &external_sender.signer,
+            &sender,
+            joiner.key_package(),
+            &retention_policy(),
+        )
+        .unwrap();
+    test_group.distribute(&external_sender.name, proposal);
+    let (commit, _) = test_group.commit("creator", |builder| builder);
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), commit)
+        .unwrap();
+    let outcome = group
+        .accept_processed_message(&provider, processed_message, &retention_policy())
+        .unwrap();
+    assert_eq!(outcome.added_members.len(), 1);
+    assert_eq!(
+        outcome.added_members[0].signature_key,
+        joiner.signature_key().as_slice()
+    );
+}
+
+#[test]
+fn external_proposals_require_a_listed_sender() {
+    let provider = provider();
+    let (test_group, mut group, _) = group_with_external_sender(&provider);
+    let other = Client::new("other");
+
+    let error = group
+        .propose_external_remove::<_, OpenMlsRustCrypto>(
+            &provider,
+            &other.signer,
+            &ExternalSender::new(other.signature_key(), other.credential().clone()),
+            test_group.leaf_index("bob"),
+            &retention_policy(),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ExternalProposalError::UnknownExternalSender
+    ));
+}
+
+/// Create a group with the member "bob".
+fn group_with_bob() -> TestGroup {
+    let mut test_group = TestGroup::new();
+    let bob = Client::new("bob");
+    let (_, welcome) = test_group.add_members(&[bob.key_package()]);
+    test_group.join(bob, welcome);
+    test_group
+}
+
+/// Process and accept the given message.
+fn accept(
+    provider: &Provider,
+    group: &mut Group,
+    assisted_message: AssistedMessageIn,
+) -> AcceptOutcome {
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), assisted_message)
+        .unwrap();
+    group
+        .accept_processed_message(provider, processed_message, &retention_policy())
+        .unwrap()
+}
+
+#[test]
+fn competing_commits_are_answered_with_the_winning_commit() {
+    let provider = provider();
+    let mut test_group = group_with_bob();
+    let mut group = test_group.assisted_group(&provider);
+
+    let losing_commit = test_group.unmerged_commit("bob", |builder| builder);
+    let (winning_commit, _) = test_group.commit("creator", |builder| builder);
+    let winning_message = winning_commit.serialized_mls_message.clone();
+    accept(&provider, &mut group, winning_commit);
+
+    let error = group
+        .process_assisted_message(provider.crypto(), losing_commit)
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        ProcessAssistedMessageError::CommitConflict {
+            winning_commit: winning_message,
+            current_epoch: group.epoch(),
+        }
+    );
+}
+
+#[test]
+fn competing_commits_of_epochs_no_longer_retained_are_rejected_as_stale() {
+    let provider = provider();
+    let mut test_group = group_with_bob();
+    let mut group = test_group.assisted_group(&provider);
+
+    let losing_commit = test_group.unmerged_commit("bob", |builder| builder);
+    // The winning commit, followed by the commits of the retained epochs.
+    for _ in 0..=RETAINED_EPOCHS {
+        let (commit, _) = test_group.commit("creator", |builder| builder);
+        accept(&provider, &mut group, commit);
+    }
+
+    let error = group
+        .process_assisted_message(provider.crypto(), losing_commit)
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        ProcessAssistedMessageError::ProcessMessageError(ProcessMessageError::ValidationError(
+            ValidationError::WrongEpoch
+        ))
+    );
+}
+
+/// Returns the epoch of the given message if it was processed as a
+/// duplicate.
+fn duplicate_epoch(
+    provider: &Provider,
+    group: &Group,
+    assisted_message: AssistedMessageIn,
+) -> Option<GroupEpoch> {
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), assisted_message)
+        .unwrap();
+    match processed_message.processed_assisted_message {
+        ProcessedAssistedMessage::Duplicate(epoch) => Some(epoch),
+        _ => None,
+    }
+}
+
+#[test]
+fn resubmitted_commits_are_duplicates() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let epoch = group.epoch();
+
+    let (commit, _) = test_group.add_members(&[Client::new("joiner").key_package()]);
+    accept(&provider, &mut group, commit.clone());
+    assert_eq!(duplicate_epoch(&provider, &group, commit), Some(epoch));
+}
+
+#[test]
+fn resubmitted_proposals_are_duplicates() {
+    let provider = provider();
+    let mut test_group = group_with_bob();
+    let mut group = test_group.assisted_group(&provider);
+
+    let proposal = test_group.propose_remove("creator", "bob");
+    assert_eq!(duplicate_epoch(&provider, &group, proposal.clone()), None);
+    accept(&provider, &mut group, proposal.clone());
+    assert_eq!(
+        duplicate_epoch(&provider, &group, proposal),
+        Some(group.epoch())
+    );
+
+    // The same proposal with different authenticated data is found in the
+    // proposal store, also after loading the group.
+    test_group
+        .member_mut("creator")
+        .mls_group
+        .set_aad(b"retry".to_vec());
+    let reencoded_proposal = test_group.propose_remove("creator", "bob");
+    let group_id = group.group_info().group_context().group_id().clone();
+    let group = Group::load(provider.storage(), &group_id).unwrap().unwrap();
+    assert_eq!(
+        duplicate_epoch(&provider, &group, reencoded_proposal),
+        Some(group.epoch())
+    );
+}
+
+/// A policy that limits the number of members added per commit and doesn't
+/// allow Remove proposals.
+struct TestPolicy {
+    max_adds: usize,
+}
+
+impl AssistPolicy for TestPolicy {
+    fn check_commit(
+        &self,
+        staged_commit: &StagedCommit,
+        _sender_leaf: Option<&LeafNode>,
+        _group_context: &GroupContext,
+    ) -> Result<(), PolicyViolation> {
+        if staged_commit.add_proposals().count() > self.max_adds {
+            return Err(PolicyViolation::GroupTooLarge);
+        }
+        Ok(())
+    }
+
+    fn check_proposal(
+        &self,
+        proposal: &QueuedProposal,
+        _sender_leaf: Option<&LeafNode>,
+        _group_context: &GroupContext,
+    ) -> Result<(), PolicyViolation> {
+        match proposal.proposal() {
+            Proposal::Remove(_) => Err(PolicyViolation::ProposalTypeNotAllowed),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn commits_violating_the_policy_are_rejected() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let group = test_group.assisted_group(&provider);
+    let policy = TestPolicy { max_adds: 1 };
+
+    let (commit, _) = test_group.add_members(&[
+        Client::new("alice").key_package(),
+        Client::new("bob").key_package(),
+    ]);
+    let error = group
+        .process_assisted_message_with_policy(provider.crypto(), commit.clone(), &policy)
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        ProcessAssistedMessageError::PolicyViolation(PolicyViolation::GroupTooLarge)
+    );
+    // Without the policy, the commit is fine.
+    assert!(
+        group
+            .process_assisted_message(provider.crypto(), commit)
+            .is_ok()
+    );
+}
+
+#[test]
+fn proposals_violating_the_policy_are_rejected() {
+    let provider = provider();
+    let mut test_group = group_with_bob();
+    let group = test_group.assisted_group(&provider);
+    let policy = TestPolicy { max_adds: 1 };
+
+    let proposal = test_group.propose_remove("creator", "bob");
+    let error = group
+        .process_assisted_message_with_policy(provider.crypto(), proposal, &policy)
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        ProcessAssistedMessageError::PolicyViolation(PolicyViolation::ProposalTypeNotAllowed)
+    );
+}
+
+/// A policy that uses the default checks, so it refuses the introduction of
+/// admin roles.
+struct DefaultPolicy;
+
+impl AssistPolicy for DefaultPolicy {}
+
+/// Returns group context extensions with admin roles for the clients with
+/// the given names.
+fn admin_roles_extensions(admins: &[&str]) -> Extensions {
+    let admins = admins
+        .iter()
+        .map(|name| BasicCredential::new(name.as_bytes().to_vec()).into())
+        .collect();
+    Extensions::from_vec(vec![
+        Extension::RequiredCapabilities(RequiredCapabilitiesExtension::new(
+            &[ExtensionType::Unknown(ADMIN_ROLES_EXTENSION_TYPE)],
+            &[],
+            &[],
+        )),
+        AdminRolesExtension::new(admins).to_extension().unwrap(),
+    ])
+    .unwrap()
+}
+
+/// Create a group with the member "bob" in which the creator is the only
+/// admin.
+fn group_with_admin() -> TestGroup {
+    let mut test_group =
+        TestGroup::with_group_context_extensions(admin_roles_extensions(&["creator"]));
+    let bob = Client::new("bob");
+    let (_, welcome) = test_group.add_members(&[bob.key_package()]);
+    test_group.join(bob, welcome);
+    test_group
+}
+
+#[test]
+fn non_admins_may_not_commit_removals_or_group_context_extensions() {
+    let provider = provider();
+    let mut test_group = group_with_admin();
+    let group = test_group.assisted_group(&provider);
+    let creator = test_group.leaf_index("creator");
+
+    let removal = test_group.unmerged_commit("bob", |builder| builder.propose_removals([creator]));
+    let group_context_extensions = test_group.unmerged_commit("bob", |builder| {
+        builder.propose_group_context_extensions(admin_roles_extensions(&["creator", "bob"]))
+    });
+    for commit in [removal, group_context_extensions] {
+        let error = group
+            .process_assisted_message(provider.crypto(), commit)
+            .err()
+            .unwrap();
+        assert_eq!(error, ProcessAssistedMessageError::MissingAdminRights);
+    }
+}
+
+#[test]
+fn admins_may_commit_removals_after_updating_their_signature_key() {
+    let provider = provider();
+    let mut test_group = group_with_admin();
+    let mut group = test_group.assisted_group(&provider);
+
+    let commit = test_group.rotate_signature_key("creator");
+    accept(&provider, &mut group, commit);
+
+    let bob = test_group.leaf_index("bob");
+    let (commit, _) = test_group.commit("creator", |builder| builder.propose_removals([bob]));
+    let outcome = accept(&provider, &mut group, commit);
+    assert_eq!(outcome.removed_members.len(), 1);
+}
+
+#[test]
+fn introducing_admin_roles_is_checked_against_the_policy() {
+    let provider = provider();
+    let mut test_group = group_with_bob();
+    let group = test_group.assisted_group(&provider);
+
+    let (commit, _) = test_group.commit("bob", |builder| {
+        builder.propose_group_context_extensions(admin_roles_extensions(&["bob"]))
+    });
+    let error = group
+        .process_assisted_message_with_policy(provider.crypto(), commit.clone(), &DefaultPolicy)
+        .err()
+        .unwrap();
+    assert_eq!(
+        error,
+        ProcessAssistedMessageError::PolicyViolation(PolicyViolation::Unauthorized)
+    );
+
+    assert!(
+        group
+            .process_assisted_message_with_policy(provider.crypto(), commit, &())
+            .is_ok()
+    );
+}
+
+#[test]
+fn accepted_adds_are_reported_as_added_members() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let bob = Client::new("bob");
+    let charlie = Client::new("charlie");
+
+    let (commit, _) = test_group.add_members(&[bob.key_package(), charlie.key_package()]);
+    let outcome = accept(&provider, &mut group, commit);
+
+    let added_signature_keys = outcome
+        .added_members
+        .iter()
+        .map(|member| member.signature_key.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        added_signature_keys,
+        [bob.signature_key(), charlie.signature_key()].map(|key| key.as_slice().to_vec())
+    );
+    assert!(outcome.removed_members.is_empty());
+    assert_eq!(outcome.epoch, group.epoch());
+    assert!(!outcome.external_join);
+}
+
+#[test]
+fn accepted_removals_are_reported_with_the_removed_members() {
+    let provider = provider();
+    let mut test_group = group_with_bob();
+    let mut group = test_group.assisted_group(&provider);
+    let bob = test_group.leaf_index("bob");
+    let bob_signature_key = test_group.member("bob").client.signature_key();
+
+    let (commit, _) = test_group.commit("creator", |builder| builder.propose_removals([bob]));
+    let outcome = accept(&provider, &mut group, commit);
+
+    assert_eq!(outcome.removed_members.len(), 1);
+    assert_eq!(outcome.removed_members[0].index, bob);
+    assert_eq!(
+        outcome.removed_members[0].signature_key,
+        bob_signature_key.as_slice()
+    );
+    assert!(outcome.added_members.is_empty());
+    assert_eq!(group.members().count(), 1);
+}
+
+#[test]
+fn accepted_updates_are_reported_with_the_updated_members() {
+    let provider = provider();
+    let mut test_group = group_with_bob();
+    let mut group = test_group.assisted_group(&provider);
+
+    let proposal = test_group.propose_self_update("bob");
+    accept(&provider, &mut group, proposal);
+    let (commit, _) = test_group.commit("creator", |builder| builder);
+    let outcome = accept(&provider, &mut group, commit);
+
+    // The committer updates their leaf with the path of the commit.
+    let mut updated_leaves = outcome
+        .updated_members
+        .iter()
+        .map(|member| member.index)
+        .collect::<Vec<_>>();
+    updated_leaves.sort();
+    assert_eq!(
+        updated_leaves,
+        [
+            test_group.leaf_index("creator"),
+            test_group.leaf_index("bob")
+        ]
+    );
+    assert!(outcome.added_members.is_empty());
+    assert!(outcome.removed_members.is_empty());
+}
+
+#[test]
+fn accepted_external_joins_are_reported_as_added_members() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let bob = Client::new("bob");
+    let bob_signature_key = bob.signature_key();
+
+    let commit = test_group.join_by_external_commit(bob);
+    let outcome = accept(&provider, &mut group, commit);
+
+    assert!(outcome.external_join);
+    assert_eq!(outcome.added_members.len(), 1);
+    assert_eq!(
+        outcome.added_members[0].signature_key,
+        bob_signature_key.as_slice()
+    );
+    assert_eq!(outcome.added_members[0].index, test_group.leaf_index("bob"));
+}
+
+/// Returns the leaf indices and signature keys of the given recipients.
+fn recipient_keys(recipients: &[Recipient]) -> Vec<(LeafNodeIndex, Vec<u8>)> {
+    recipients
+        .iter()
+        .map(|recipient| {
+            (
+                recipient.leaf_index,
+                recipient.signature_key.as_slice().to_vec(),
+            )
+        })
+        .collect()
+}
+
+/// Returns the leaf index and signature key of the given member.
+fn member_keys(test_group: &TestGroup, name: &str) -> (LeafNodeIndex, Vec<u8>) {
+    (
+        test_group.leaf_index(name),
+        test_group
+            .member(name)
+            .client
+            .signature_key()
+            .as_slice()
+            .to_vec(),
+    )
+}
+
+#[test]
+fn private_messages_are_sent_to_all_members_of_the_current_epoch() {
+    let provider = provider();
+    let mut test_group = group_with_bob();
+    let mut group = test_group.assisted_group(&provider);
+
+    let message = test_group.application_message("bob");
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), message.clone())
+        .unwrap();
+    let recipients = group
+        .recipients_for(&processed_message.processed_assisted_message)
+        .unwrap();
+    assert_eq!(
+        recipient_keys(&recipients.members),
+        [
+            member_keys(&test_group, "creator"),
+            member_keys(&test_group, "bob")
+        ]
+    );
+    assert!(recipients.welcome_recipients.is_empty());
+
+    // Once the group moved on, the members of the message's epoch are
+    // unknown.
+    let (commit, _) = test_group.commit("creator", |builder| builder);
+    accept(&provider, &mut group, commit);
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), message)
+        .unwrap();
+    let error = group
+        .recipients_for(&processed_message.processed_assisted_message)
+        .unwrap_err();
+    assert_eq!(
+        error,
+        RecipientsError::WrongEpoch {
+            message_epoch: GroupEpoch::from(1),
+            current_epoch: GroupEpoch::from(2),
+        }
+    );
+}
+
+#[test]
+fn commits_are_sent_to_removed_members_but_not_the_committer() {
+    let provider = provider();
+    let mut test_group = group_with_bob();
+    let mut group = test_group.assisted_group(&provider);
+    let charlie = Client::new("charlie");
+    let (commit, welcome) = test_group.add_members(&[charlie.key_package()]);
+    accept(&provider, &mut group, commit);
+    test_group.join(charlie, welcome);
+    let bob = member_keys(&test_group, "bob");
+    let charlie = member_keys(&test_group, "charlie");
+
+    let (commit, _) = test_group.commit("charlie", |builder| builder.propose_removals([bob.0]));
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), commit)
+        .unwrap();
+    let recipients = group
+        .recipients_for(&processed_message.processed_assisted_message)
+        .unwrap();
+    assert_eq!(
+        recipient_keys(&recipients.members),
+        [member_keys(&test_group, "creator"), bob]
+    );
+    assert!(!recipient_keys(&recipients.members).contains(&charlie));
+    assert!(recipients.welcome_recipients.is_empty());
+}
+
+#[test]
+fn clients_added_by_a_commit_are_welcome_recipients() {
+    let provider = provider();
+    let mut test_group = group_with_bob();
+    let group = test_group.assisted_group(&provider);
+    let charlie = Client::new("charlie");
+
+    let (commit, _) = test_group.add_members(&[charlie.key_package()]);
+    let processed_message = group
+        .process_assisted_message(provider.crypto(), commit)
+        .unwrap();
+    let recipients = group
+        .recipients_for(&processed_message.processed_assisted_message)
+        .unwrap();
+    assert_eq!(
+        recipient_keys(&recipients.members),
+        [member_keys(&test_group, "bob")]
+    );
+    assert_eq!(recipients.welcome_recipients, [charlie.signature_key()]);
+}
+
+/// Let the creator add the given clients and accept the commit. Returns the
+/// outcome, the Welcome and the refs of the clients' key packages.
+fn add_and_accept(
+    provider: &Provider,
+    test_group: &mut TestGroup,
+    group: &mut Group,
+    clients: &[Client],
+) -> (AcceptOutcome, MlsMessageOut, Vec<KeyPackageRef>) {
+    let key_packages = clients.iter().map(Client::key_package).collect::<Vec<_>>();
+    let (commit, welcome) = test_group.add_members(&key_packages);
+    let outcome = accept(provider, group, commit);
+    let key_package_refs = key_packages
+        .iter()
+        .map(|key_package| key_package.hash_ref(provider.crypto()).unwrap())
+        .collect();
+    (outcome, welcome, key_package_refs)
+}
+
+#[test]
+fn welcomes_are_validated_against_the_leaves_of_the_joiners() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let clients = ["bob", "charlie"].map(Client::new);
+
+    let (outcome, welcome, key_package_refs) =
+        add_and_accept(&provider, &mut test_group, &mut group, &clients);
+    let joiners = group
+        .validate_welcome(&outcome, &assisted_welcome(welcome.clone()))
+        .unwrap();
+    for client in clients {
+        test_group.join(client, welcome.clone());
+    }
+
+    assert_eq!(
+        joiners,
+        HashMap::from([
+            (key_package_refs[0].clone(), test_group.leaf_index("bob")),
+            (
+                key_package_refs[1].clone(),
+                test_group.leaf_index("charlie")
+            ),
+        ])
+    );
+}
+
+#[test]
+fn joiners_are_validated_with_the_leaves_freed_by_removals() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let clients = ["bob", "charlie"].map(Client::new);
+    let (_, welcome, _) = add_and_accept(&provider, &mut test_group, &mut group, &clients);
+    for client in clients {
+        test_group.join(client, welcome.clone());
+    }
+    let bob = test_group.leaf_index("bob");
+    let (commit, _) = test_group.commit("creator", |builder| builder.propose_removals([bob]));
+    accept(&provider, &mut group, commit);
+
+    // Dave fills bob's leaf, eve extends the tree.
+    let clients = ["dave", "eve"].map(Client::new);
+    let (outcome, welcome, key_package_refs) =
+        add_and_accept(&provider, &mut test_group, &mut group, &clients);
+    let joiners = group
+        .validate_welcome(&outcome, &assisted_welcome(welcome.clone()))
+        .unwrap();
+    for client in clients {
+        test_group.join(client, welcome.clone());
+    }
+
+    assert_eq!(test_group.leaf_index("dave"), bob);
+    assert_eq!(
+        joiners,
+        HashMap::from([
+            (key_package_refs[0].clone(), bob),
+            (key_package_refs[1].clone(), test_group.leaf_index("eve")),
+        ])
+    );
+}
+
+#[test]
+fn welcomes_to_clients_not_added_by_the_commit_are_rejected() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let bob_key_package = Client::new("bob").key_package();
+    let charlie_key_package = Client::new("charlie").key_package();
+
+    let (commit, _) = test_group.add_members(std::slice::from_ref(&bob_key_package));
+    let outcome = accept(&provider, &mut group, commit);
+    // The Welcome of another group that adds bob and charlie.
+    let (_, welcome) =
+        TestGroup::new().add_members(&[bob_key_package, charlie_key_package.clone()]);
+
+    assert_eq!(
+        group
+            .validate_welcome(&outcome, &assisted_welcome(welcome))
+            .unwrap_err(),
+        WelcomeValidationError::UnknownJoiner(
+            charlie_key_package.hash_ref(provider.crypto()).unwrap()
+        )
+    );
+}
+
+#[test]
+fn welcomes_missing_clients_added_by_the_commit_are_rejected() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let bob_key_package = Client::new("bob").key_package();
+    let charlie_key_package = Client::new("charlie").key_package();
+
+    let (commit, _) =
+        test_group.add_members(&[bob_key_package.clone(), charlie_key_package.clone()]);
+    let outcome = accept(&provider, &mut group, commit);
+    // The Welcome of another group that only adds bob.
+    let (_, welcome) = TestGroup::new().add_members(&[bob_key_package]);
+
+    assert_eq!(
+        group
+            .validate_welcome(&outcome, &assisted_welcome(welcome))
+            .unwrap_err(),
+        WelcomeValidationError::MissingJoiner(
+            charlie_key_package.hash_ref(provider.crypto()).unwrap()
+        )
+    );
+}
+
+#[test]
+fn removed_joiners_lose_access_to_past_group_states_after_updating_their_key() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let clients = ["bob", "charlie"].map(Client::new);
+    let (_, welcome, key_package_refs) =
+        add_and_accept(&provider, &mut test_group, &mut group, &clients);
+    let epoch = group.epoch();
+    for client in clients {
+        test_group.join(client, welcome.clone());
+    }
+
+    let commit = test_group.rotate_signature_key("bob");
+    accept(&provider, &mut group, commit);
+    let bob = test_group.leaf_index("bob");
+    let (commit, _) = test_group.commit("creator", |builder| builder.propose_removals([bob]));
+    accept(&provider, &mut group, commit);
+
+    let joiner = |key_package_ref| {
+        group
+            .past_group_states
+            .joiner_signature_key(&epoch, key_package_ref)
+    };
+    assert!(joiner(&key_package_refs[0]).is_none());
+    assert_eq!(
+        joiner(&key_package_refs[1]),
+        Some(&test_group.member("charlie").client.signature_key())
+    );
+}
+
+/// Returns the past group state of the given epoch for the given joiner,
+/// requested at the current time of the provider's clock.
+fn past_group_state(
+    provider: &Provider,
+    group: &Group,
+    joiner: &Client,
+    epoch: GroupEpoch,
+) -> Option<RatchetTreeIn> {
+    let request = request(
+        provider,
+        group,
+        joiner,
+        JoinerId::SignatureKey(joiner.signature_key()),
+        epoch,
+    );
+    group
+        .past_group_state(provider, &request, &retention_policy())
+        .unwrap()
+}
+
+#[test]
+fn past_group_states_expire_according_to_the_provider_clock() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let joiner = Client::new("joiner");
+    let epoch = add_joiner(&provider, &mut test_group, &mut group, &joiner);
+
+    provider
+        .clock()
+        .advance(retention_policy().max_age() - Duration::seconds(1));
+    assert!(past_group_state(&provider, &group, &joiner, epoch).is_some());
+
+    provider.clock().advance(Duration::seconds(1));
+    assert!(past_group_state(&provider, &group, &joiner, epoch).is_none());
+}
+
+#[test]
+fn past_group_states_expired_by_the_provider_clock_are_evicted_on_accept() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let joiner = Client::new("joiner");
+    add_joiner(&provider, &mut test_group, &mut group, &joiner);
+
+    provider.clock().advance(Duration::hours(12));
+    let (commit, _) = test_group.add_members(&[Client::new("other").key_package()]);
+    let outcome = accept(&provider, &mut group, commit);
+    assert_eq!(outcome.past_group_state_evictions, EvictionStats::default());
+
+    provider.clock().advance(Duration::hours(12));
+    let (commit, _) = test_group.commit("creator", |builder| builder);
+    let outcome = accept(&provider, &mut group, commit);
+    assert_eq!(outcome.past_group_state_evictions.expired, 1);
+    assert!(outcome.past_group_state_evictions.freed_bytes > 0);
+}
+
+#[test]
+fn expired_past_group_states_are_refused_and_pruned() {
+    let provider = provider();
+    let mut test_group = TestGroup::new();
+    let mut group = test_group.assisted_group(&provider);
+    let joiner = Client::new("joiner");
+    let (_, _, key_package_refs) = add_and_accept(
+        &provider,
+        &mut test_group,
+        &mut group,
+        std::slice::from_ref(&joiner),
+    );
+    let epoch = group.epoch();
+    let group_id = group.group_info().group_context().group_id().clone();
+    let stored_joiner = |provider: &Provider| {
+        Group::load(provider.storage(), &group_id)
+            .unwrap()
+            .unwrap()
+            .past_group_states
+            .joiner_signature_key(&epoch, &key_package_refs[0])
+            .cloned()
+    };
+
+    // Expired states are refused before they are pruned.
+    provider.clock().advance(retention_policy().max_age());
+    assert!(past_group_state(&provider, &group, &joiner, epoch).is_none());
+    assert_eq!(stored_joiner(&provider), Some(joiner.signature_key()));
+
+    let stats = group.prune(&provider, &retention_policy()).unwrap();
+    assert_eq!(stats.expired, 1);
+    assert!(stats.freed_bytes > 0);
+    assert_eq!(stored_joiner(&provider), None);
+}
+
+#[test]
+fn expired_past_group_states_are_swept() {
+    let provider = provider();
+    let mut groups = Vec::new();
+    for _ in 0..2 {
+        let mut test_group = TestGroup::new();
+        let mut group = test_group.assisted_group(&provider);
+        let (_, _, key_package_refs) = add_and_accept(
+            &provider,
+            &mut test_group,
+            &mut group,
+            &[Client::new("joiner")],
+        );
+        groups.push((group, key_package_refs));
+    }
+
+    let stats = sweep_past_group_states(&provider, &retention_policy()).unwrap();
+    assert_eq!(stats.groups, 2);
+    assert_eq!(stats.evictions, EvictionStats::default());
+
+    provider.clock().advance(Duration::days(2));
+    let stats = sweep_past_group_states(&provider, &retention_policy()).unwrap();
+    assert_eq!(stats.groups, 2);
+    assert_eq!(stats.deleted_records, 2);
+    assert_eq!(stats.evictions.expired, 2);
+    assert!(stats.evictions.freed_bytes > 0);
+
+    // The groups themselves are kept without their past group states.
+    for (group, key_package_refs) in groups {
+        let group_id = group.group_info().group_context().group_id();
+        let loaded_group = Group::load(provider.storage(), group_id).unwrap().unwrap();
+        assert!(
+            loaded_group
+                .past_group_states
+                .joiner_signature_key(&group.epoch(), &key_package_refs[0])
+                .is_none()
+        );
+    }
+    let stats = sweep_past_group_states(&provider, &retention_policy()).unwrap();
+    assert_eq!(stats.groups, 0);
+}
+
+#[cfg(feature = "async")]
+mod async_storage {
+    use crate::{
+        async_provider::{
+            AsyncMlsAssistProvider, AsyncMlsAssistRustCrypto, AsyncMlsAssistStorageProvider,
+            RecordKind,
+        },
+        group::past_group_states::sweep_past_group_states_async,
+        memory_provider::Codec,
+        test_utils::block_on,
+    };
+
+    use super::*;
+
+    type AsyncProvider = AsyncMlsAssistRustCrypto<JsonCodec, TestClock>;
+
+    fn async_provider() -> AsyncProvider {
+        AsyncProvider::new(Default::default(), TestClock::new(Utc::now()))
+    }
+
+    /// Create the assisted group and add a joiner, so that one past group
+    /// state is stored.
+    fn group_with_past_group_state(provider: &AsyncProvider) -> Group {
+        let mut test_group = TestGroup::new();
+        let (verifiable_group_info, ratchet_tree) = test_group.group_info_and_tree();
+        let mut group = block_on(Group::new_async(
+            provider,
+            verifiable_group_info,
+            ratchet_tree,
+        ))
+        .unwrap();
+        let (commit, _) = test_group.add_members(&[Client::new("joiner").key_package()]);
+        let processed_message = group
+            .process_assisted_message(provider.crypto(), commit)
+            .unwrap();
+        block_on(group.accept_processed_message_async(
+            provider,
+            processed_message,
+            &retention_policy(),
+        ))
+        .unwrap();
+        group
+    }
+
+    /// Returns the number of stored past group state records of the group.
+    fn past_group_state_records(provider: &AsyncProvider, group: &Group) -> usize {
+        let group_id = group.group_info().group_context().group_id();
+        let group_id_bytes = JsonCodec::to_vec(group_id).unwrap();
+        block_on(provider.storage().read_group(&group_id_bytes))
+            .unwrap()
+            .iter()
+            .filter(|record| record.kind == RecordKind::PastGroupState)
+            .count()
+    }
+
+    #[test]
+    fn groups_round_trip_through_async_storage() {
+        let provider = async_provider();
+        let group = group_with_past_group_state(&provider);
+        let group_id = group.group_info().group_context().group_id().clone();
+        assert_eq!(past_group_state_records(&provider, &group), 1);
+
+        let loaded_group = block_on(Group::load_async(&provider, &group_id))
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded_group.epoch(), group.epoch());
+        assert_eq!(
+            loaded_group.export_ratchet_tree(),
+            group.export_ratchet_tree()
+        );
+
+        block_on(Group::delete_async(&provider, &group_id)).unwrap();
+        assert!(
+            block_on(Group::load_async(&provider, &group_id))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn expired_past_group_states_are_pruned_from_async_storage() {
+        let provider = async_provider();
+        let mut group = group_with_past_group_state(&provider);
+
+        let stats = block_on(group.prune_async(&provider, &retention_policy())).unwrap();
+        assert_eq!(stats.expired, 0);
+        assert_eq!(past_group_state_records(&provider, &group), 1);
+
+        provider.clock().advance(Duration::days(2));
+        let stats = block_on(group.prune_async(&provider, &retention_policy())).unwrap();
+        assert_eq!(stats.expired, 1);
+        assert_eq!(past_group_state_records(&provider, &group), 0);
+    }
+
+    #[test]
+    fn expired_past_group_states_are_swept_from_async_storage() {
+        let provider = async_provider();
+        let group = group_with_past_group_state(&provider);
+        let other_group = group_with_past_group_state(&provider);
+
+        let stats = block_on(sweep_past_group_states_async(
+            &provider,
+            &retention_policy(),
+        ))
+        .unwrap();
+        assert_eq!(stats.groups, 2);
+        assert_eq!(stats.evictions.expired, 0);
+
+        provider.clock().advance(Duration::days(2));
+        let stats = block_on(sweep_past_group_states_async(
+            &provider,
+            &retention_policy(),
+        ))
+        .unwrap();
+        assert_eq!(stats.groups, 2);
+        assert_eq!(stats.deleted_records, 2);
+        assert_eq!(stats.evictions.expired, 2);
+        assert_eq!(past_group_state_records(&provider, &group), 0);
+        assert_eq!(past_group_state_records(&provider, &other_group), 0);
+
+        // The groups themselves are kept.
+        let group_id = group.group_info().group_context().group_id();
+        assert!(
+            block_on(Group::load_async(&provider, group_id))
+                .unwrap()
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,323 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Content-addressed storage for serialized ratchet trees.
+//!
+//! Trees are split into chunks at content-defined boundaries, so that the
+//! parts of the tree that don't change between epochs yield the same chunks
+//! and are only stored once, no matter how many past group states refer to
+//! them.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Chunks are never smaller than this, except for the last chunk of a tree.
+const MIN_CHUNK_SIZE: usize = 2 * 1024;
+/// Chunks are never larger than this.
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+/// A chunk ends where the rolling hash has none of these bits set. With 13
+/// bits, chunks are around 8 KiB in size on average.
+const CHUNK_BOUNDARY_MASK: u64 = ((1 << 13) - 1) << (64 - 13);
+
+/// Random values for the rolling hash, one per byte value.
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    // Generated using splitmix64.
+    let mut table = [0u64; 256];
+    let mut state = 0u64;
+    let mut index = 0;
+    while index < table.len() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[index] = value ^ (value >> 31);
+        index += 1;
+    }
+    table
+}
+
+/// Split the given bytes into chunks at content-defined boundaries.
+fn content_defined_chunks(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut hash = 0u64;
+    for (index, byte) in bytes.iter().enumerate() {
+        hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+        let length = index + 1 - start;
+        if (length >= MIN_CHUNK_SIZE && hash & CHUNK_BOUNDARY_MASK == 0) || length >= MAX_CHUNK_SIZE
+        {
+            chunks.push(&bytes[start..=index]);
+            start = index + 1;
+            hash = 0;
+        }
+    }
+    if start < bytes.len() {
+        chunks.push(&bytes[start..]);
+    }
+    chunks
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(super) struct ChunkHash([u8; 32]);
+
+impl ChunkHash {
+    fn new(chunk: &[u8]) -> Self {
+        Self(Sha256::digest(chunk).into())
+    }
+}
+
+struct Chunk {
+    bytes: Vec<u8>,
+    /// Number of trees that contain this chunk.
+    references: usize,
+}
+
+/// Reference counted chunks of serialized trees. Chunks are persisted
+/// individually, so the store keeps track of the chunks that were added or
+/// removed since it was last written to storage.
+#[derive(Default)]
+pub(super) struct ChunkStore {
+    chunks: HashMap<ChunkHash, Chunk>,
+    changed_chunks: HashSet<ChunkHash>,
+}
+
+impl ChunkStore {
+    /// Add a reference to the chunk with the given hash, loading it with
+    /// `load_chunk` if it isn't in the store yet. Chunks that can't be loaded
+    /// are skipped, so that trees containing them can't be assembled.
+    pub(super) fn add_reference<E>(
+        &mut self,
+        chunk_hash: ChunkHash,
+        load_chunk: impl FnOnce(&ChunkHash) -> Result<Option<Vec<u8>>, E>,
+    ) -> Result<(), E> {
+        if let Some(chunk) = self.chunks.get_mut(&chunk_hash) {
+            chunk.references += 1;
+        } else if let Some(bytes) = load_chunk(&chunk_hash)? {
+            self.chunks.insert(
+                chunk_hash,
+                Chunk {
+                    bytes,
+                    references: 1,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Store the given serialized tree. Returns the hashes of its chunks from
+    /// which it can be reassembled.
+    pub(super) fn insert(&mut self, tree_bytes: &[u8]) -> Vec<ChunkHash> {
+        content_defined_chunks(tree_bytes)
+            .into_iter()
+            .map(|chunk_bytes| {
+                let chunk_hash = ChunkHash::new(chunk_bytes);
+                self.chunks
+                    .entry(chunk_hash)
+                    .or_insert_with(|| {
+                        self.changed_chunks.insert(chunk_hash);
+                        Chunk {
+                            bytes: chunk_bytes.to_vec(),
+                            references: 0,
+                        }
+                    })
+                    .references += 1;
+                chunk_hash
+            })
+            .collect()
+    }
+
+    /// Reassemble a serialized tree from the given chunks. Returns `None` if
+    /// one of the chunks is missing.
+    pub(super) fn assemble(&self, chunk_hashes: &[ChunkHash]) -> Option<Vec<u8>> {
+        let mut tree_bytes = Vec::new();
+        for chunk_hash in chunk_hashes {
+            tree_bytes.extend_from_slice(&self.chunks.get(chunk_hash)?.bytes);
+        }
+        Some(tree_bytes)
+    }
+
+    /// Release the given chunks of a tree that is no longer needed. Chunks that
+    /// are not part of any other tree are removed. Returns the number of bytes
+    /// freed.
+    pub(super) fn release(&mut self, chunk_hashes: &[ChunkHash]) -> usize {
+        let mut freed_bytes = 0;
+        for chunk_hash in chunk_hashes {
+            let Some(chunk) = self.chunks.get_mut(chunk_hash) else {
+                continue;
+            };
+            chunk.references -= 1;
+            if chunk.references == 0 {
+                freed_bytes += chunk.bytes.len();
+                self.chunks.remove(chunk_hash);
+                self.changed_chunks.insert(*chunk_hash);
+            }
+        }
+        freed_bytes
+    }
+
+    /// Returns the chunks that were added or removed since the last call, with
+    /// their bytes if they were added and `None` if they were removed.
+    pub(super) fn take_changes(&mut self) -> Vec<(ChunkHash, Option<&[u8]>)> {
+        std::mem::take(&mut self.changed_chunks)
+            .into_iter()
+            .map(|chunk_hash| {
+                let bytes = self
+                    .chunks
+                    .get(&chunk_hash)
+                    .map(|chunk| chunk.bytes.as_slice());
+                (chunk_hash, bytes)
+            })
+            .collect()
+    }
+
+    /// Returns the total size of all stored chunks in bytes.
+    pub(super) fn total_size(&self) -> usize {
+        self.chunks.values().map(|chunk| chunk.bytes.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns `length` pseudorandom bytes derived from `seed`.
+    fn random_bytes(seed: u64, length: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..length)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chunks_respect_size_limits() {
+        for bytes in [random_bytes(1, 1 << 20), vec![0; 1 << 20]] {
+            let chunks = content_defined_chunks(&bytes);
+            let (last, chunks_but_last) = chunks.split_last().unwrap();
+            assert!(last.len() <= MAX_CHUNK_SIZE);
+            for chunk in chunks_but_last {
+                assert!((MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk.len()));
+            }
+            assert_eq!(chunks.concat(), bytes);
+        }
+    }
+
+    #[test]
+    fn short_input_is_a_single_chunk() {
+        let bytes = random_bytes(2, MIN_CHUNK_SIZE - 1);
+        assert_eq!(content_defined_chunks(&bytes), vec![bytes.as_slice()]);
+        assert!(content_defined_chunks(&[]).is_empty());
+    }
+
+    #[test]
+    fn boundaries_resynchronize_after_an_insertion() {
+        let bytes = random_bytes(3, 1 << 20);
+        let mut edited_bytes = bytes.clone();
+        edited_bytes.splice(bytes.len() / 2..bytes.len() / 2, [0xaa; 100]);
+
+        let chunks = content_defined_chunks(&bytes);
+        let edited_chunks = content_defined_chunks(&edited_bytes);
+        let differing_chunks = edited_chunks
+            .iter()
+            .filter(|chunk| !chunks.contains(chunk))
+            .count();
+        assert!(chunks.len() > 16);
+        assert!(differing_chunks <= 2, "{differing_chunks} chunks differ");
+    }
+
+    #[test]
+    fn shared_chunks_are_stored_once() {
+        let mut chunk_store = ChunkStore::default();
+        let tree = random_bytes(4, 256 * 1024);
+        let mut edited_tree = tree.clone();
+        edited_tree[1000] ^= 0xff;
+
+        let chunks = chunk_store.insert(&tree);
+        let edited_chunks = chunk_store.insert(&edited_tree);
+        let new_chunks = edited_chunks
+            .iter()
+            .filter(|chunk_hash| !chunks.contains(chunk_hash))
+            .count();
+        assert!(new_chunks <= 2);
+        assert!(chunk_store.total_size() < tree.len() + MAX_CHUNK_SIZE * new_chunks);
+        assert_eq!(chunk_store.assemble(&chunks).unwrap(), tree);
+        assert_eq!(chunk_store.assemble(&edited_chunks).unwrap(), edited_tree);
+    }
+
+    #[test]
+    fn chunks_are_removed_with_their_last_reference() {
+        let mut chunk_store = ChunkStore::default();
+        let tree = random_bytes(5, 64 * 1024);
+        let chunks = chunk_store.insert(&tree);
+        let added = chunk_store.take_changes();
+        assert_eq!(added.len(), chunks.len());
+        assert!(added.iter().all(|(_, bytes)| bytes.is_some()));
+
+        // Inserting the same tree again only adds references.
+        assert_eq!(chunk_store.insert(&tree), chunks);
+        assert!(chunk_store.take_changes().is_empty());
+
+        assert_eq!(chunk_store.release(&chunks), 0);
+        assert!(chunk_store.take_changes().is_empty());
+        assert_eq!(chunk_store.assemble(&chunks).unwrap(), tree);
+
+        assert_eq!(chunk_store.release(&chunks), tree.len());
+        let removed = chunk_store.take_changes();
+        assert_eq!(removed.len(), chunks.len());
+        assert!(removed.iter().all(|(_, bytes)| bytes.is_none()));
+        assert_eq!(chunk_store.total_size(), 0);
+        assert_eq!(chunk_store.assemble(&chunks), None);
+    }
+
+    #[test]
+    fn loaded_chunks_are_reference_counted() {
+        let tree = random_bytes(6, 64 * 1024);
+        let mut stored_chunks = ChunkStore::default();
+        let chunks = stored_chunks.insert(&tree);
+        let stored_chunks = stored_chunks
+            .take_changes()
+            .into_iter()
+            .map(|(chunk_hash, bytes)| (chunk_hash, bytes.unwrap().to_vec()))
+            .collect::<HashMap<_, _>>();
+
+        let mut chunk_store = ChunkStore::default();
+        let mut loads = 0;
+        for _ in 0..2 {
+            for chunk_hash in &chunks {
+                chunk_store
+                    .add_reference::<()>(*chunk_hash, |chunk_hash| {
+                        loads += 1;
+                        Ok(stored_chunks.get(chunk_hash).cloned())
+                    })
+                    .unwrap();
+            }
+        }
+        assert_eq!(loads, chunks.len());
+        assert!(chunk_store.take_changes().is_empty());
+        assert_eq!(chunk_store.release(&chunks), 0);
+        assert_eq!(chunk_store.assemble(&chunks).unwrap(), tree);
+        assert_eq!(chunk_store.release(&chunks), tree.len());
+    }
+
+    #[test]
+    fn missing_chunks_prevent_assembly() {
+        let tree = random_bytes(7, 64 * 1024);
+        let chunks = ChunkStore::default().insert(&tree);
+        let mut chunk_store = ChunkStore::default();
+        for chunk_hash in &chunks {
+            chunk_store
+                .add_reference::<()>(*chunk_hash, |_| Ok(None))
+                .unwrap();
+        }
+        assert_eq!(chunk_store.assemble(&chunks), None);
+    }
+}
@@ -0,0 +1,69 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use openmls::prelude::{Ciphersuite, KeyPackageRef};
+
+use crate::{
+    messages::AssistedWelcome,
+    tls_codec::{Deserialize as _, Serialize as _},
+};
+
+use super::{
+    errors::{LibraryError, WelcomeValidationError},
+    *,
+};
+
+impl Group {
+    /// Check that the given Welcome belongs to the commit with the given
+    /// outcome, i.e. that its ciphersuite matches the group's and that it is
+    /// addressed to exactly the joiners added by the commit.
+    ///
+    /// This has to be called after the commit was accepted, as the leaves of
+    /// the joiners are only known once the commit is merged. Returns the leaf
+    /// index each joiner was added at indexed by the [`KeyPackageRef`] the
+    /// Welcome addresses them with.
+    pub fn validate_welcome(
+        &self,
+        accept_outcome: &AcceptOutcome,
+        assisted_welcome: &AssistedWelcome,
+    ) -> Result<HashMap<KeyPackageRef, LeafNodeIndex>, WelcomeValidationError> {
+        if welcome_ciphersuite(assisted_welcome)? != self.public_group.group_context().ciphersuite()
+        {
+            return Err(WelcomeValidationError::CiphersuiteMismatch);
+        }
+        let joiners = assisted_welcome.joiners().collect::<Vec<_>>();
+        if let Some(joiner) = joiners
+            .iter()
+            .find(|joiner| !accept_outcome.joiners.contains_key(joiner))
+        {
+            return Err(WelcomeValidationError::UnknownJoiner(joiner.clone()));
+        }
+        if let Some(key_package_ref) = accept_outcome
+            .joiners
+            .keys()
+            .find(|key_package_ref| !joiners.contains(key_package_ref))
+        {
+            return Err(WelcomeValidationError::MissingJoiner(
+                key_package_ref.clone(),
+            ));
+        }
+        Ok(accept_outcome.joiners.clone())
+    }
+}
+
+/// Returns the ciphersuite of the given Welcome, which leads its encoding.
+fn welcome_ciphersuite(
+    assisted_welcome: &AssistedWelcome,
+) -> Result<Ciphersuite, WelcomeValidationError> {
+    let serialized_welcome = assisted_welcome
+        .welcome
+        .tls_serialize_detached()
+        .map_err(|_| LibraryError::LibraryError)?;
+    Ok(
+        Ciphersuite::tls_deserialize(&mut serialized_welcome.as_slice())
+            .map_err(|_| LibraryError::LibraryError)?,
+    )
+}
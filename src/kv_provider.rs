@@ -0,0 +1,794 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! An adapter that implements [`MlsAssistStorageProvider`] on top of any
+//! [`KeyValueStore`].
+//!
+//! # Key layout
+//!
+//! Every key starts with a one byte namespace identifying the kind of record,
+//! followed by the length of the encoded group id as a big-endian `u32`, the
+//! encoded group id and the encoded record key. Records of which a group has
+//! only one have the encoding of `()` as record key.
+//!
+//! | Namespace | Record                      | Record key   |
+//! |-----------|-----------------------------|--------------|
+//! | `0x01`    | TreeSync tree               | `()`         |
+//! | `0x02`    | Group context               | `()`         |
+//! | `0x03`    | Interim transcript hash     | `()`         |
+//! | `0x04`    | Confirmation tag            | `()`         |
+//! | `0x05`    | Queued proposal             | Proposal ref |
+//! | `0x06`    | Group info                  | `()`         |
+//! | `0x07`    | Accepted messages           | `()`         |
+//! | `0x08`    | Past group state            | Epoch        |
+//! | `0x09`    | Past group state tree chunk | Chunk hash   |
+//!
+//! The length prefix keeps the keys of a group from being a prefix of the
+//! keys of another group and allows recovering the group id from a key.
+//!
+//! Group ids and record keys are encoded with the adapter's [`Codec`], as are
+//! all values.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    marker::PhantomData,
+    sync::{Mutex, MutexGuard},
+};
+
+use openmls_traits::{
+    public_storage::PublicStorageProvider,
+    storage::{
+        CURRENT_VERSION,
+        traits::{self, GroupId},
+    },
+};
+use serde::{Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+use crate::{
+    group::errors::StorageError, memory_provider::Codec, provider_traits::MlsAssistStorageProvider,
+    transaction_lock::TransactionLock,
+};
+
+/// A write operation on a [`KeyValueStore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KvOperation {
+    Put { key: Vec<u8>, value: Vec<u8> },
+    Delete { key: Vec<u8> },
+}
+
+/// Entries of a [`KeyValueStore`] as pairs of key and value.
+pub type KvEntries = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// A minimal key-value store that [`KvStorage`] turns into a complete
+/// MLS-assist storage provider.
+pub trait KeyValueStore {
+    type Error: std::error::Error;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error>;
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), Self::Error>;
+
+    fn delete(&self, key: &[u8]) -> Result<(), Self::Error>;
+
+    /// Returns all entries whose key starts with the given prefix.
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<KvEntries, Self::Error>;
+
+    /// Returns the keys of all entries whose key starts with the given
+    /// prefix. Stores that can read keys without their values should
+    /// override this. The default implementation drops the values of
+    /// [`Self::scan_prefix`].
+    fn scan_prefix_keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error> {
+        Ok(self
+            .scan_prefix(prefix)?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect())
+    }
+
+    /// Apply the given operations in order. Stores that can apply them
+    /// atomically should override this, since [`KvStorage`] commits
+    /// transactions with a single batch. The default implementation applies
+    /// them one by one.
+    fn batch(&self, operations: Vec<KvOperation>) -> Result<(), Self::Error> {
+        for operation in operations {
+            match operation {
+                KvOperation::Put { key, value } => self.put(&key, &value)?,
+                KvOperation::Delete { key } => self.delete(&key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error returned by [`KvStorage`].
+#[derive(Debug, Error)]
+pub enum KvStorageError<S: std::error::Error, C: std::error::Error> {
+    /// Error of the underlying key-value store.
+    #[error(transparent)]
+    Store(S),
+    /// Error encoding or decoding a key or value.
+    #[error(transparent)]
+    Codec(C),
+}
+
+/// The first byte of a key, identifying the kind of record.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum Namespace {
+    Tree = 0x01,
+    Context = 0x02,
+    InterimTranscriptHash = 0x03,
+    ConfirmationTag = 0x04,
+    Proposal = 0x05,
+    GroupInfo = 0x06,
+    AcceptedMessages = 0x07,
+    PastGroupState = 0x08,
+    PastGroupStateChunk = 0x09,
+}
+
+pub(crate) const NAMESPACES: [Namespace; 9] = [
+    Namespace::Tree,
+    Namespace::Context,
+    Namespace::InterimTranscriptHash,
+    Namespace::ConfirmationTag,
+    Namespace::Proposal,
+    Namespace::GroupInfo,
+    Namespace::AcceptedMessages,
+    Namespace::PastGroupState,
+    Namespace::PastGroupStateChunk,
+];
+
+/// Returns the prefix of all keys of the given namespace and group.
+fn group_prefix(namespace: Namespace, group_id_bytes: &[u8]) -> Vec<u8> {
+    let mut prefix = Vec::with_capacity(5 + group_id_bytes.len());
+    prefix.push(namespace as u8);
+    prefix.extend_from_slice(&(group_id_bytes.len() as u32).to_be_bytes());
+    prefix.extend_from_slice(group_id_bytes);
+    prefix
+}
+
+/// Returns the encoded group id of the given key.
+fn key_group_id(key: &[u8]) -> Option<&[u8]> {
+    let length_bytes = key.get(1..5)?.try_into().ok()?;
+    let length = u32::from_be_bytes(length_bytes) as usize;
+    key.get(5..5 + length)
+}
+
+/// Result of the helpers of [`KvStorage`].
+type KvResult<T, S, C> =
+    Result<T, KvStorageError<<S as KeyValueStore>::Error, <C as Codec>::Error>>;
+
+/// Writes that were made in a transaction and not yet committed. `None`
+/// marks a deleted key.
+type Overlay = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// An [`MlsAssistStorageProvider`] on top of a [`KeyValueStore`]. Keys and
+/// values are encoded with the codec `C`.
+///
+/// Writes made in a transaction are kept in memory and committed to the
+/// store with a single [`KeyValueStore::batch`], so transactions are atomic
+/// if the store's batches are. Only one thread at a time runs transactions,
+/// and their writes are only visible to that thread until they are
+/// committed.
+pub struct KvStorage<S: KeyValueStore, C: Codec> {
+    store: S,
+    transaction_lock: TransactionLock,
+    /// The writes of the running transaction, one overlay per nesting level.
+    overlays: Mutex<Vec<Overlay>>,
+    _codec: PhantomData<C>,
+}
+
+impl<S: KeyValueStore, C: Codec> KvStorage<S, C> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            transaction_lock: TransactionLock::default(),
+            overlays: Mutex::default(),
+            _codec: PhantomData,
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    fn encode(value: &impl Serialize) -> KvResult<Vec<u8>, S, C> {
+        C::to_vec(value).map_err(KvStorageError::Codec)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> KvResult<T, S, C> {
+        C::from_slice(bytes).map_err(KvStorageError::Codec)
+    }
+
+    /// Returns the overlays of the running transaction if the current thread
+    /// runs one.
+    fn overlays(&self) -> Option<MutexGuard<'_, Vec<Overlay>>> {
+        self.transaction_lock
+            .is_held_by_current_thread()
+            .then(|| self.overlays.lock().unwrap())
+    }
+
+    fn get(&self, key: &[u8]) -> KvResult<Option<Vec<u8>>, S, C> {
+        if let Some(overlays) = self.overlays()
+            && let Some(value) = overlays.iter().rev().find_map(|overlay| overlay.get(key))
+        {
+            return Ok(value.clone());
+        }
+        self.store.get(key).map_err(KvStorageError::Store)
+    }
+
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> KvResult<(), S, C> {
+        if let Some(overlay) = self
+            .overlays()
+            .as_mut()
+            .and_then(|overlays| overlays.last_mut())
+        {
+            overlay.insert(key, Some(value));
+            return Ok(());
+        }
+        self.store.put(&key, &value).map_err(KvStorageError::Store)
+    }
+
+    fn delete(&self, key: Vec<u8>) -> KvResult<(), S, C> {
+        if let Some(overlay) = self
+            .overlays()
+            .as_mut()
+            .and_then(|overlays| overlays.last_mut())
+        {
+            overlay.insert(key, None);
+            return Ok(());
+        }
+        self.store.delete(&key).map_err(KvStorageError::Store)
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> KvResult<KvEntries, S, C> {
+        let entries = self
+            .store
+            .scan_prefix(prefix)
+            .map_err(KvStorageError::Store)?;
+        let Some(overlays) = self.overlays() else {
+            return Ok(entries);
+        };
+        let mut entries = entries.into_iter().collect::<BTreeMap<_, _>>();
+        for overlay in overlays.iter() {
+            for (key, value) in overlay.range(prefix.to_vec()..) {
+                if !key.starts_with(prefix) {
+                    break;
+                }
+                match value {
+                    Some(value) => entries.insert(key.clone(), value.clone()),
+                    None => entries.remove(key),
+                };
+            }
+        }
+        Ok(entries.into_iter().collect())
+    }
+
+    fn scan_prefix_keys(&self, prefix: &[u8]) -> KvResult<Vec<Vec<u8>>, S, C> {
+        let keys = self
+            .store
+            .scan_prefix_keys(prefix)
+            .map_err(KvStorageError::Store)?;
+        let Some(overlays) = self.overlays() else {
+            return Ok(keys);
+        };
+        let mut keys = keys.into_iter().collect::<BTreeSet<_>>();
+        for overlay in overlays.iter() {
+            for (key, value) in overlay.range(prefix.to_vec()..) {
+                if !key.starts_with(prefix) {
+                    break;
+                }
+                match value {
+                    Some(_) => keys.insert(key.clone()),
+                    None => keys.remove(key),
+                };
+            }
+        }
+        Ok(keys.into_iter().collect())
+    }
+
+    fn record_key(
+        namespace: Namespace,
+        group_id: &impl Serialize,
+        key: &impl Serialize,
+    ) -> KvResult<Vec<u8>, S, C> {
+        let mut record_key = group_prefix(namespace, &Self::encode(group_id)?);
+        record_key.extend_from_slice(&Self::encode(key)?);
+        Ok(record_key)
+    }
+
+    fn write(
+        &self,
+        group_id: &impl Serialize,
+        namespace: Namespace,
+        key: &impl Serialize,
+        value: &impl Serialize,
+    ) -> KvResult<(), S, C> {
+        let record_key = Self::record_key(namespace, group_id, key)?;
+        self.put(record_key, Self::encode(value)?)
+    }
+
+    fn read<T: DeserializeOwned>(
+        &self,
+        group_id: &impl Serialize,
+        namespace: Namespace,
+        key: &impl Serialize,
+    ) -> KvResult<Option<T>, S, C> {
+        let record_key = Self::record_key(namespace, group_id, key)?;
+        let Some(value) = self.get(&record_key)? else {
+            return Ok(None);
+        };
+        Self::decode(&value).map(Some)
+    }
+
+    fn remove(
+        &self,
+        group_id: &impl Serialize,
+        namespace: Namespace,
+        key: &impl Serialize,
+    ) -> KvResult<(), S, C> {
+        let record_key = Self::record_key(namespace, group_id, key)?;
+        self.delete(record_key)
+    }
+
+    /// Delete all records of the given namespace.
+    fn remove_all(&self, group_id: &impl Serialize, namespace: Namespace) -> KvResult<(), S, C> {
+        let prefix = group_prefix(namespace, &Self::encode(group_id)?);
+        for key in self.scan_prefix_keys(&prefix)? {
+            self.delete(key)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the record keys and values of all records of the given
+    /// namespace.
+    fn read_all(
+        &self,
+        group_id: &impl Serialize,
+        namespace: Namespace,
+    ) -> KvResult<KvEntries, S, C> {
+        let prefix = group_prefix(namespace, &Self::encode(group_id)?);
+        Ok(self
+            .scan_prefix(&prefix)?
+            .into_iter()
+            .map(|(key, value)| (key[prefix.len()..].to_vec(), value))
+            .collect())
+    }
+}
+
+impl<S: KeyValueStore, C: Codec> PublicStorageProvider<CURRENT_VERSION> for KvStorage<S, C> {
+    /// An opaque error returned by all methods on this trait.
+    type PublicError = KvStorageError<S::Error, C::Error>;
+
+    /// Write the TreeSync tree.
+    fn write_tree<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        TreeSync: traits::TreeSync<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        tree: &TreeSync,
+    ) -> Result<(), Self::PublicError> {
+        self.write(group_id, Namespace::Tree, &(), tree)
+    }
+
+    /// Write the interim transcript hash.
+    fn write_interim_transcript_hash<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        InterimTranscriptHash: traits::InterimTranscriptHash<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        interim_transcript_hash: &InterimTranscriptHash,
+    ) -> Result<(), Self::PublicError> {
+        self.write(
+            group_id,
+            Namespace::InterimTranscriptHash,
+            &(),
+            interim_transcript_hash,
+        )
+    }
+
+    /// Write the group context.
+    fn write_context<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        GroupContext: traits::GroupContext<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        group_context: &GroupContext,
+    ) -> Result<(), Self::PublicError> {
+        self.write(group_id, Namespace::Context, &(), group_context)
+    }
+
+    /// Write the confirmation tag.
+    fn write_confirmation_tag<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ConfirmationTag: traits::ConfirmationTag<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        confirmation_tag: &ConfirmationTag,
+    ) -> Result<(), Self::PublicError> {
+        self.write(group_id, Namespace::ConfirmationTag, &(), confirmation_tag)
+    }
+
+    /// Enqueue a proposal.
+    fn queue_proposal<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+        QueuedProposal: traits::QueuedProposal<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+        proposal: &QueuedProposal,
+    ) -> Result<(), Self::PublicError> {
+        self.write(group_id, Namespace::Proposal, proposal_ref, proposal)
+    }
+
+    /// Returns all queued proposals for the group with group id `group_id`, or an empty vector of none are stored.
+    fn queued_proposals<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+        QueuedProposal: traits::QueuedProposal<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Vec<(ProposalRef, QueuedProposal)>, Self::PublicError> {
+        self.read_all(group_id, Namespace::Proposal)?
+            .iter()
+            .map(|(proposal_ref_bytes, proposal_bytes)| {
+                Ok((
+                    Self::decode(proposal_ref_bytes)?,
+                    Self::decode(proposal_bytes)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Returns the TreeSync tree for the group with group id `group_id`.
+    fn tree<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        TreeSync: traits::TreeSync<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<TreeSync>, Self::PublicError> {
+        self.read(group_id, Namespace::Tree, &())
+    }
+
+    /// Returns the group context for the group with group id `group_id`.
+    fn group_context<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        GroupContext: traits::GroupContext<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<GroupContext>, Self::PublicError> {
+        self.read(group_id, Namespace::Context, &())
+    }
+
+    /// Returns the interim transcript hash for the group with group id `group_id`.
+    fn interim_transcript_hash<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        InterimTranscriptHash: traits::InterimTranscriptHash<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<InterimTranscriptHash>, Self::PublicError> {
+        self.read(group_id, Namespace::InterimTranscriptHash, &())
+    }
+
+    /// Returns the confirmation tag for the group with group id `group_id`.
+    fn confirmation_tag<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ConfirmationTag: traits::ConfirmationTag<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<ConfirmationTag>, Self::PublicError> {
+        self.read(group_id, Namespace::ConfirmationTag, &())
+    }
+
+    /// Deletes the tree from storage
+    fn delete_tree<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.remove(group_id, Namespace::Tree, &())
+    }
+
+    /// Deletes the confirmation tag from storage
+    fn delete_confirmation_tag<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.remove(group_id, Namespace::ConfirmationTag, &())
+    }
+
+    /// Deletes the group context for the group with given id
+    fn delete_context<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.remove(group_id, Namespace::Context, &())
+    }
+
+    /// Deletes the interim transcript hash for the group with given id
+    fn delete_interim_transcript_hash<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.remove(group_id, Namespace::InterimTranscriptHash, &())
+    }
+
+    /// Removes an individual proposal from the proposal queue of the group with the provided id
+    fn remove_proposal<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+    ) -> Result<(), Self::PublicError> {
+        self.remove(group_id, Namespace::Proposal, proposal_ref)
+    }
+
+    /// Clear the proposal queue for the group with the given id.
+    fn clear_proposal_queue<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.remove_all(group_id, Namespace::Proposal)
+    }
+}
+
+impl<S: KeyValueStore, C: Codec> MlsAssistStorageProvider for KvStorage<S, C> {
+    /// Writes made by `f` are committed to the store with a single
+    /// [`KeyValueStore::batch`] if it succeeds and discarded otherwise.
+    /// Writes of nested transactions become part of the enclosing one if
+    /// they succeed.
+    fn with_transaction<T, F>(
+        &self,
+        f: impl FnOnce() -> Result<T, F>,
+    ) -> Result<Result<T, F>, StorageError<Self>> {
+        let transaction = self.transaction_lock.enter();
+        {
+            let mut overlays = self.overlays.lock().unwrap();
+            if transaction.is_outermost() {
+                // Discard the writes of a transaction that was aborted by a
+                // panic.
+                overlays.clear();
+            }
+            overlays.push(Overlay::new());
+        }
+        let result = f();
+        let mut overlays = self.overlays.lock().unwrap();
+        let overlay = overlays.pop().unwrap_or_default();
+        if result.is_err() {
+            return Ok(result);
+        }
+        if let Some(enclosing_overlay) = overlays.last_mut() {
+            enclosing_overlay.extend(overlay);
+            return Ok(result);
+        }
+        drop(overlays);
+        let operations = overlay
+            .into_iter()
+            .map(|(key, value)| match value {
+                Some(value) => KvOperation::Put { key, value },
+                None => KvOperation::Delete { key },
+            })
+            .collect();
+        self.store
+            .batch(operations)
+            .map_err(KvStorageError::Store)?;
+        Ok(result)
+    }
+
+    fn group_ids<GroupId: DeserializeOwned>(&self) -> Result<Vec<GroupId>, StorageError<Self>> {
+        let mut group_id_bytes = BTreeSet::new();
+        for namespace in NAMESPACES {
+            for key in self.scan_prefix_keys(&[namespace as u8])? {
+                if let Some(bytes) = key_group_id(&key) {
+                    group_id_bytes.insert(bytes.to_vec());
+                }
+            }
+        }
+        group_id_bytes
+            .iter()
+            .map(|group_id_bytes| Self::decode(group_id_bytes))
+            .collect()
+    }
+
+    fn write_past_group_state(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl Serialize,
+        past_group_state: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write(group_id, Namespace::PastGroupState, epoch, past_group_state)
+    }
+
+    fn read_past_group_state<PastGroupState: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl Serialize,
+    ) -> Result<Option<PastGroupState>, StorageError<Self>> {
+        self.read(group_id, Namespace::PastGroupState, epoch)
+    }
+
+    fn delete_past_group_state(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.remove(group_id, Namespace::PastGroupState, epoch)
+    }
+
+    fn past_group_state_epochs<Epoch: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Vec<Epoch>, StorageError<Self>> {
+        self.read_all(group_id, Namespace::PastGroupState)?
+            .iter()
+            .map(|(epoch_bytes, _)| Self::decode(epoch_bytes))
+            .collect()
+    }
+
+    fn write_past_group_state_chunk(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl Serialize,
+        chunk: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write(group_id, Namespace::PastGroupStateChunk, chunk_hash, chunk)
+    }
+
+    fn read_past_group_state_chunk<Chunk: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl Serialize,
+    ) -> Result<Option<Chunk>, StorageError<Self>> {
+        self.read(group_id, Namespace::PastGroupStateChunk, chunk_hash)
+    }
+
+    fn delete_past_group_state_chunk(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.remove(group_id, Namespace::PastGroupStateChunk, chunk_hash)
+    }
+
+    fn delete_past_group_states(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), StorageError<Self>> {
+        self.remove_all(group_id, Namespace::PastGroupState)?;
+        self.remove_all(group_id, Namespace::PastGroupStateChunk)
+    }
+
+    fn write_accepted_messages(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        accepted_messages: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write(
+            group_id,
+            Namespace::AcceptedMessages,
+            &(),
+            accepted_messages,
+        )
+    }
+
+    fn read_accepted_messages<AcceptedMessages: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Option<AcceptedMessages>, StorageError<Self>> {
+        self.read(group_id, Namespace::AcceptedMessages, &())
+    }
+
+    fn delete_accepted_messages(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), StorageError<Self>> {
+        self.remove(group_id, Namespace::AcceptedMessages, &())
+    }
+
+    fn write_group_info(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        group_info: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write(group_id, Namespace::GroupInfo, &(), group_info)
+    }
+
+    fn read_group_info<GroupInfo: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Option<GroupInfo>, StorageError<Self>> {
+        self.read(group_id, Namespace::GroupInfo, &())
+    }
+
+    fn delete_group_info(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), StorageError<Self>> {
+        self.remove(group_id, Namespace::GroupInfo, &())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use openmls::prelude::GroupId;
+
+    use crate::test_utils::{JsonCodec, MemoryStore};
+
+    use super::*;
+
+    #[test]
+    fn transactions_whose_batch_fails_leave_the_store_unchanged() {
+        let storage = KvStorage::<MemoryStore, JsonCodec>::new(MemoryStore::default());
+        let group_id = GroupId::from_slice(&[1]);
+        storage.store().fail_batches(true);
+
+        let result = storage.with_transaction(|| {
+            storage.write_group_info(&group_id, &"failed")?;
+            storage.write_accepted_messages(&group_id, &"failed")
+        });
+
+        assert!(matches!(result, Err(KvStorageError::Store(_))));
+        assert_eq!(storage.store().len(), 0);
+        assert_eq!(storage.read_group_info::<String>(&group_id).unwrap(), None);
+
+        storage.store().fail_batches(false);
+        storage
+            .with_transaction(|| storage.write_group_info(&group_id, &"committed"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(storage.store().len(), 1);
+        assert_eq!(
+            storage
+                .read_group_info::<String>(&group_id)
+                .unwrap()
+                .as_deref(),
+            Some("committed")
+        );
+    }
+
+    #[test]
+    fn uncommitted_writes_are_invisible_to_other_threads() {
+        let storage = KvStorage::<MemoryStore, JsonCodec>::new(MemoryStore::default());
+        let group_id = GroupId::from_slice(&[1]);
+        storage
+            .with_transaction(|| {
+                storage.write_group_info(&group_id, &"uncommitted")?;
+                thread::scope(|scope| {
+                    let group_info = scope
+                        .spawn(|| storage.read_group_info::<String>(&group_id).unwrap())
+                        .join()
+                        .unwrap();
+                    assert_eq!(group_info, None);
+                });
+                Ok::<_, KvStorageError<_, _>>(())
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            storage
+                .read_group_info::<String>(&group_id)
+                .unwrap()
+                .as_deref(),
+            Some("uncommitted")
+        );
+    }
+}
@@ -9,7 +9,19 @@ pub use openmls_traits;
 
 pub use memory_provider::MlsAssistRustCrypto;
 
+#[cfg(feature = "async")]
+pub mod async_provider;
 pub mod group;
+pub mod kv_provider;
 pub mod memory_provider;
 pub mod messages;
 pub mod provider_traits;
+#[cfg(feature = "redb")]
+pub mod redb_provider;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_provider;
+#[cfg(test)]
+mod storage_tests;
+#[cfg(test)]
+mod test_utils;
+mod transaction_lock;
@@ -3,11 +3,13 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     marker::PhantomData,
-    sync::RwLock,
+    sync::{Arc, Mutex, RwLock},
 };
 
+use chrono::{DateTime, Duration, Utc};
+use openmls::group::GroupId as OpenMlsGroupId;
 use openmls_rust_crypto::RustCrypto;
 use openmls_traits::{
     public_storage::PublicStorageProvider,
@@ -19,11 +21,12 @@ use openmls_traits::{
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use crate::{
-    group::errors::StorageError,
-    provider_traits::{MlsAssistProvider, MlsAssistStorageProvider},
+    group::{errors::StorageError, past_group_states::LegacyPastGroupStates},
+    provider_traits::{Clock, MlsAssistProvider, MlsAssistStorageProvider, SystemClock},
+    transaction_lock::TransactionLock,
 };
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 struct PublicGroupState {
     treesync: Vec<u8>,
     interim_transcript_hash: Vec<u8>,
@@ -57,15 +60,258 @@ pub trait Codec {
     fn from_slice<T: DeserializeOwned>(data: &[u8]) -> Result<T, Self::Error>;
 }
 
+/// Records of a group, keyed by group id and then by record key.
+type GroupRecords = RwLock<HashMap<Vec<u8>, HashMap<Vec<u8>, Vec<u8>>>>;
+
+/// Returns the previous value of the record.
+fn write_record(
+    records: &GroupRecords,
+    group_id_bytes: Vec<u8>,
+    key_bytes: Vec<u8>,
+    value_bytes: Vec<u8>,
+) -> Option<Vec<u8>> {
+    let mut records = records.write().unwrap();
+    records
+        .entry(group_id_bytes)
+        .or_default()
+        .insert(key_bytes, value_bytes)
+}
+
+fn read_record(records: &GroupRecords, group_id_bytes: &[u8], key_bytes: &[u8]) -> Option<Vec<u8>> {
+    let records = records.read().unwrap();
+    records.get(group_id_bytes)?.get(key_bytes).cloned()
+}
+
+/// Returns the previous value of the record.
+fn delete_record(
+    records: &GroupRecords,
+    group_id_bytes: &[u8],
+    key_bytes: &[u8],
+) -> Option<Vec<u8>> {
+    let mut records = records.write().unwrap();
+    let group_records = records.get_mut(group_id_bytes)?;
+    let previous = group_records.remove(key_bytes);
+    if group_records.is_empty() {
+        records.remove(group_id_bytes);
+    }
+    previous
+}
+
+/// Set the value of the given group back to `previous`.
+fn restore<V>(values: &RwLock<HashMap<Vec<u8>, V>>, group_id_bytes: Vec<u8>, previous: Option<V>) {
+    let mut values = values.write().unwrap();
+    match previous {
+        Some(previous) => values.insert(group_id_bytes, previous),
+        None => values.remove(&group_id_bytes),
+    };
+}
+
+#[derive(Clone, Copy)]
+enum Records {
+    PastGroupStates,
+    PastGroupStateChunks,
+}
+
+#[derive(Clone, Copy)]
+enum Values {
+    AcceptedMessages,
+    GroupInfos,
+}
+
+/// A change made in a transaction, with the previous state needed to revert
+/// it.
+enum Change {
+    Record {
+        records: Records,
+        group_id_bytes: Vec<u8>,
+        key_bytes: Vec<u8>,
+        previous: Option<Vec<u8>>,
+    },
+    GroupRecords {
+        records: Records,
+        group_id_bytes: Vec<u8>,
+        previous: Option<HashMap<Vec<u8>, Vec<u8>>>,
+    },
+    Value {
+        values: Values,
+        group_id_bytes: Vec<u8>,
+        previous: Option<Vec<u8>>,
+    },
+    GroupState {
+        group_id_bytes: Vec<u8>,
+        previous: Option<PublicGroupState>,
+    },
+}
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct MlsAssistMemoryStorage<C: Codec> {
-    past_group_states: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
+    past_group_states: GroupRecords,
+    past_group_state_chunks: GroupRecords,
+    accepted_messages: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
     group_infos: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
     group_states: RwLock<HashMap<Vec<u8>, PublicGroupState>>,
+    #[serde(skip)]
+    transaction_lock: TransactionLock,
+    /// The changes made by the running transaction, in order.
+    #[serde(skip)]
+    changes: Mutex<Vec<Change>>,
     _codec: PhantomData<C>,
 }
 
 impl<C: Codec> MlsAssistMemoryStorage<C> {
+    fn records(&self, records: Records) -> &GroupRecords {
+        match records {
+            Records::PastGroupStates => &self.past_group_states,
+            Records::PastGroupStateChunks => &self.past_group_state_chunks,
+        }
+    }
+
+    fn values(&self, values: Values) -> &RwLock<HashMap<Vec<u8>, Vec<u8>>> {
+        match values {
+            Values::AcceptedMessages => &self.accepted_messages,
+            Values::GroupInfos => &self.group_infos,
+        }
+    }
+
+    /// Returns whether writes of the current thread are part of a
+    /// transaction.
+    fn in_transaction(&self) -> bool {
+        self.transaction_lock.is_held_by_current_thread()
+    }
+
+    /// Record the given change if it was made in a transaction.
+    fn log(&self, change: impl FnOnce() -> Change) {
+        if self.in_transaction() {
+            self.changes.lock().unwrap().push(change());
+        }
+    }
+
+    fn revert(&self, change: Change) {
+        match change {
+            Change::Record {
+                records,
+                group_id_bytes,
+                key_bytes,
+                previous,
+            } => {
+                let records = self.records(records);
+                match previous {
+                    Some(previous) => {
+                        write_record(records, group_id_bytes, key_bytes, previous);
+                    }
+                    None => {
+                        delete_record(records, &group_id_bytes, &key_bytes);
+                    }
+                }
+            }
+            Change::GroupRecords {
+                records,
+                group_id_bytes,
+                previous,
+            } => restore(self.records(records), group_id_bytes, previous),
+            Change::Value {
+                values,
+                group_id_bytes,
+                previous,
+            } => restore(self.values(values), group_id_bytes, previous),
+            Change::GroupState {
+                group_id_bytes,
+                previous,
+            } => restore(&self.group_states, group_id_bytes, previous),
+        }
+    }
+
+    fn write_record(
+        &self,
+        records: Records,
+        group_id_bytes: Vec<u8>,
+        key_bytes: Vec<u8>,
+        value_bytes: Vec<u8>,
+    ) {
+        let previous = write_record(
+            self.records(records),
+            group_id_bytes.clone(),
+            key_bytes.clone(),
+            value_bytes,
+        );
+        self.log(|| Change::Record {
+            records,
+            group_id_bytes,
+            key_bytes,
+            previous,
+        });
+    }
+
+    fn delete_record(&self, records: Records, group_id_bytes: Vec<u8>, key_bytes: Vec<u8>) {
+        let previous = delete_record(self.records(records), &group_id_bytes, &key_bytes);
+        self.log(|| Change::Record {
+            records,
+            group_id_bytes,
+            key_bytes,
+            previous,
+        });
+    }
+
+    fn delete_group_records(&self, records: Records, group_id_bytes: Vec<u8>) {
+        let previous = self
+            .records(records)
+            .write()
+            .unwrap()
+            .remove(&group_id_bytes);
+        self.log(|| Change::GroupRecords {
+            records,
+            group_id_bytes,
+            previous,
+        });
+    }
+
+    fn write_value(&self, values: Values, group_id_bytes: Vec<u8>, value_bytes: Vec<u8>) {
+        let previous = self
+            .values(values)
+            .write()
+            .unwrap()
+            .insert(group_id_bytes.clone(), value_bytes);
+        self.log(|| Change::Value {
+            values,
+            group_id_bytes,
+            previous,
+        });
+    }
+
+    fn delete_value(&self, values: Values, group_id_bytes: Vec<u8>) {
+        let previous = self.values(values).write().unwrap().remove(&group_id_bytes);
+        self.log(|| Change::Value {
+            values,
+            group_id_bytes,
+            previous,
+        });
+    }
+
+    /// Update the public group state of the given group. It is removed if it
+    /// is empty afterwards.
+    fn update_group_state(
+        &self,
+        group_id_bytes: Vec<u8>,
+        update: impl FnOnce(&mut PublicGroupState),
+    ) {
+        let mut group_states = self.group_states.write().unwrap();
+        let previous = self
+            .in_transaction()
+            .then(|| group_states.get(&group_id_bytes).cloned());
+        let public_group_state = group_states.entry(group_id_bytes.clone()).or_default();
+        update(public_group_state);
+        if public_group_state.is_empty() {
+            group_states.remove(&group_id_bytes);
+        }
+        drop(group_states);
+        if let Some(previous) = previous {
+            self.log(|| Change::GroupState {
+                group_id_bytes,
+                previous,
+            });
+        }
+    }
+
     fn write_payload<
         GroupId: traits::GroupId<CURRENT_VERSION>,
         Payload: Entity<CURRENT_VERSION>,
@@ -77,9 +323,7 @@ impl<C: Codec> MlsAssistMemoryStorage<C> {
     ) -> Result<(), C::Error> {
         let group_id_bytes = C::to_vec(group_id)?;
         let payload_bytes = C::to_vec(payload)?;
-        let mut group_states = self.group_states.write().unwrap();
-        let public_group_state = group_states.entry(group_id_bytes).or_default();
-        match data_type {
+        self.update_group_state(group_id_bytes, |public_group_state| match data_type {
             DataType::TreeSync => {
                 public_group_state.treesync = payload_bytes;
             }
@@ -92,7 +336,7 @@ impl<C: Codec> MlsAssistMemoryStorage<C> {
             DataType::ConfirmationTag => {
                 public_group_state.confirmation_tag = payload_bytes;
             }
-        };
+        });
         Ok(())
     }
 
@@ -125,26 +369,20 @@ impl<C: Codec> MlsAssistMemoryStorage<C> {
         data_type: DataType,
     ) -> Result<(), C::Error> {
         let group_id_bytes = C::to_vec(group_id)?;
-        let mut group_states = self.group_states.write().unwrap();
-        if let Some(public_group_state) = group_states.get_mut(&group_id_bytes) {
-            match data_type {
-                DataType::TreeSync => {
-                    public_group_state.treesync.clear();
-                }
-                DataType::InterimTranscriptHash => {
-                    public_group_state.interim_transcript_hash.clear();
-                }
-                DataType::Context => {
-                    public_group_state.context.clear();
-                }
-                DataType::ConfirmationTag => {
-                    public_group_state.confirmation_tag.clear();
-                }
-            };
-            if public_group_state.is_empty() {
-                group_states.remove(&group_id_bytes);
+        self.update_group_state(group_id_bytes, |public_group_state| match data_type {
+            DataType::TreeSync => {
+                public_group_state.treesync.clear();
             }
-        }
+            DataType::InterimTranscriptHash => {
+                public_group_state.interim_transcript_hash.clear();
+            }
+            DataType::Context => {
+                public_group_state.context.clear();
+            }
+            DataType::ConfirmationTag => {
+                public_group_state.confirmation_tag.clear();
+            }
+        });
         Ok(())
     }
 }
@@ -219,11 +457,11 @@ impl<C: Codec> PublicStorageProvider<CURRENT_VERSION> for MlsAssistMemoryStorage
         let group_id_bytes = C::to_vec(group_id)?;
         let proposal_ref_bytes = C::to_vec(proposal_ref)?;
         let proposal_bytes = C::to_vec(proposal)?;
-        let mut group_states = self.group_states.write().unwrap();
-        let public_group_state = group_states.entry(group_id_bytes).or_default();
-        public_group_state
-            .proposal_queue
-            .insert(proposal_ref_bytes, proposal_bytes);
+        self.update_group_state(group_id_bytes, |public_group_state| {
+            public_group_state
+                .proposal_queue
+                .insert(proposal_ref_bytes, proposal_bytes);
+        });
         Ok(())
     }
 
@@ -336,15 +574,11 @@ impl<C: Codec> PublicStorageProvider<CURRENT_VERSION> for MlsAssistMemoryStorage
     ) -> Result<(), Self::PublicError> {
         let group_id_bytes = C::to_vec(group_id)?;
         let proposal_ref_bytes = C::to_vec(proposal_ref)?;
-        let mut group_states = self.group_states.write().unwrap();
-        if let Some(public_group_state) = group_states.get_mut(&group_id_bytes) {
+        self.update_group_state(group_id_bytes, |public_group_state| {
             public_group_state
                 .proposal_queue
                 .remove(&proposal_ref_bytes);
-            if public_group_state.is_empty() {
-                group_states.remove(&group_id_bytes);
-            }
-        }
+        });
         Ok(())
     }
 
@@ -357,24 +591,59 @@ impl<C: Codec> PublicStorageProvider<CURRENT_VERSION> for MlsAssistMemoryStorage
         group_id: &GroupId,
     ) -> Result<(), Self::PublicError> {
         let group_id_bytes = C::to_vec(group_id)?;
-        let mut group_states = self.group_states.write().unwrap();
-        if let Some(public_group_state) = group_states.get_mut(&group_id_bytes) {
+        self.update_group_state(group_id_bytes, |public_group_state| {
             public_group_state.proposal_queue.clear();
-            if public_group_state.is_empty() {
-                group_states.remove(&group_id_bytes);
-            }
-        }
+        });
         Ok(())
     }
 }
 
+/// The records of each group as pairs of key and value bytes.
+type SerializedRecords = Vec<(Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>)>;
+
 #[derive(Default, Serialize, Deserialize)]
 struct SerializableMlsAssistMemoryStorage {
     storage_bytes: Vec<(Vec<u8>, Vec<u8>)>,
+    /// The past group states of each group as a single value, as serialized
+    /// before they were stored as separate records. They are migrated when
+    /// deserializing and never serialized.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     past_group_states_bytes: Vec<(Vec<u8>, Vec<u8>)>,
+    #[serde(default)]
+    past_group_state_records_bytes: SerializedRecords,
+    #[serde(default)]
+    past_group_state_chunks_bytes: SerializedRecords,
+    #[serde(default)]
+    accepted_messages_bytes: Vec<(Vec<u8>, Vec<u8>)>,
     group_infos_bytes: Vec<(Vec<u8>, Vec<u8>)>,
 }
 
+fn serialize_records(records: &GroupRecords) -> SerializedRecords {
+    records
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(group_id_bytes, group_records)| {
+            let group_records = group_records
+                .iter()
+                .map(|(key_bytes, value_bytes)| (key_bytes.clone(), value_bytes.clone()))
+                .collect();
+            (group_id_bytes.clone(), group_records)
+        })
+        .collect()
+}
+
+fn deserialize_records(records: SerializedRecords) -> GroupRecords {
+    RwLock::new(
+        records
+            .into_iter()
+            .map(|(group_id_bytes, group_records)| {
+                (group_id_bytes, group_records.into_iter().collect())
+            })
+            .collect(),
+    )
+}
+
 impl<C: Codec> MlsAssistMemoryStorage<C> {
     pub fn serialize(&self) -> Result<Vec<u8>, C::Error> {
         let storage = self.group_states.read().unwrap();
@@ -382,13 +651,15 @@ impl<C: Codec> MlsAssistMemoryStorage<C> {
             .iter()
             .map(|(key, value)| Ok((key.clone(), C::to_vec(value)?)))
             .collect::<Result<Vec<_>, _>>()?;
-        let past_group_states_bytes = self
-            .past_group_states
+        let past_group_state_records_bytes = serialize_records(&self.past_group_states);
+        let past_group_state_chunks_bytes = serialize_records(&self.past_group_state_chunks);
+        let accepted_messages_bytes = self
+            .accepted_messages
             .read()
             .unwrap()
             .iter()
-            .map(|(group_id_bytes, past_group_states_bytes)| {
-                (group_id_bytes.clone(), past_group_states_bytes.clone())
+            .map(|(group_id_bytes, accepted_messages_bytes)| {
+                (group_id_bytes.clone(), accepted_messages_bytes.clone())
             })
             .collect();
         let group_infos_bytes = self
@@ -402,16 +673,25 @@ impl<C: Codec> MlsAssistMemoryStorage<C> {
             .collect();
         let serialized = SerializableMlsAssistMemoryStorage {
             storage_bytes,
-            past_group_states_bytes,
+            past_group_states_bytes: Vec::new(),
+            past_group_state_records_bytes,
+            past_group_state_chunks_bytes,
+            accepted_messages_bytes,
             group_infos_bytes,
         };
         C::to_vec(&serialized)
     }
 
+    /// Deserialize a storage serialized with [`Self::serialize`]. Past group
+    /// states serialized by earlier versions are migrated to the current
+    /// layout. Returns an error if they can't be decoded.
     pub fn deserialize(serialized: &[u8]) -> Result<Self, C::Error> {
         let deserialized: SerializableMlsAssistMemoryStorage = C::from_slice(serialized)?;
-        let past_group_states =
-            RwLock::new(deserialized.past_group_states_bytes.into_iter().collect());
+        let past_group_states = deserialize_records(deserialized.past_group_state_records_bytes);
+        let past_group_state_chunks =
+            deserialize_records(deserialized.past_group_state_chunks_bytes);
+        let accepted_messages =
+            RwLock::new(deserialized.accepted_messages_bytes.into_iter().collect());
         let group_infos = RwLock::new(deserialized.group_infos_bytes.into_iter().collect());
         let storage = Self {
             group_states: RwLock::new(
@@ -422,36 +702,199 @@ impl<C: Codec> MlsAssistMemoryStorage<C> {
                     .collect::<Result<HashMap<_, _>, _>>()?,
             ),
             past_group_states,
+            past_group_state_chunks,
+            accepted_messages,
             group_infos,
+            transaction_lock: TransactionLock::default(),
+            changes: Mutex::default(),
             _codec: PhantomData,
         };
+        for (group_id_bytes, past_group_states_bytes) in deserialized.past_group_states_bytes {
+            let group_id: OpenMlsGroupId = C::from_slice(&group_id_bytes)?;
+            let legacy_past_group_states: LegacyPastGroupStates =
+                C::from_slice(&past_group_states_bytes)?;
+            legacy_past_group_states.migrate(&storage, &group_id)?;
+        }
         Ok(storage)
     }
 }
 
 impl<C: Codec> MlsAssistStorageProvider for MlsAssistMemoryStorage<C> {
-    fn write_past_group_states(
+    /// Transactions record the previous state of everything they change and
+    /// restore it if they are rolled back. Only one thread at a time runs
+    /// transactions. Writes made by other threads outside of transactions
+    /// are not rolled back, unless they overwrite records written by the
+    /// transaction.
+    fn with_transaction<T, E>(
+        &self,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Result<T, E>, StorageError<Self>> {
+        let transaction = self.transaction_lock.enter();
+        if transaction.is_outermost() {
+            // Discard the changes of a transaction that was aborted by a panic.
+            self.changes.lock().unwrap().clear();
+        }
+        let savepoint = self.changes.lock().unwrap().len();
+        let result = f();
+        let changes = self.changes.lock().unwrap().split_off(savepoint);
+        if result.is_err() {
+            for change in changes.into_iter().rev() {
+                self.revert(change);
+            }
+        } else if !transaction.is_outermost() {
+            self.changes.lock().unwrap().extend(changes);
+        }
+        Ok(result)
+    }
+
+    fn group_ids<GroupId: DeserializeOwned>(&self) -> Result<Vec<GroupId>, StorageError<Self>> {
+        let mut group_id_bytes = BTreeSet::new();
+        group_id_bytes.extend(self.group_infos.read().unwrap().keys().cloned());
+        group_id_bytes.extend(self.past_group_states.read().unwrap().keys().cloned());
+        group_id_bytes.extend(self.accepted_messages.read().unwrap().keys().cloned());
+        group_id_bytes
+            .iter()
+            .map(|group_id_bytes| C::from_slice(group_id_bytes))
+            .collect()
+    }
+
+    fn write_past_group_state(
         &self,
         group_id: &impl GroupId<CURRENT_VERSION>,
-        past_group_states: &impl serde::Serialize,
+        epoch: &impl serde::Serialize,
+        past_group_state: &impl serde::Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write_record(
+            Records::PastGroupStates,
+            C::to_vec(group_id)?,
+            C::to_vec(epoch)?,
+            C::to_vec(past_group_state)?,
+        );
+        Ok(())
+    }
+
+    fn read_past_group_state<PastGroupState: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl serde::Serialize,
+    ) -> Result<Option<PastGroupState>, StorageError<Self>> {
+        let group_id_bytes = C::to_vec(group_id)?;
+        let epoch_bytes = C::to_vec(epoch)?;
+        let Some(past_group_state_bytes) =
+            read_record(&self.past_group_states, &group_id_bytes, &epoch_bytes)
+        else {
+            return Ok(None);
+        };
+        C::from_slice(&past_group_state_bytes).map(Some)
+    }
+
+    fn delete_past_group_state(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl serde::Serialize,
     ) -> Result<(), StorageError<Self>> {
         let group_id_bytes = C::to_vec(group_id)?;
-        let past_group_states_bytes = C::to_vec(past_group_states)?;
-        let mut past_group_states = self.past_group_states.write().unwrap();
-        past_group_states.insert(group_id_bytes, past_group_states_bytes);
+        let epoch_bytes = C::to_vec(epoch)?;
+        self.delete_record(Records::PastGroupStates, group_id_bytes, epoch_bytes);
         Ok(())
     }
 
-    fn read_past_group_states<PastGroupStates: DeserializeOwned>(
+    fn past_group_state_epochs<Epoch: DeserializeOwned>(
         &self,
         group_id: &impl GroupId<CURRENT_VERSION>,
-    ) -> Result<Option<PastGroupStates>, StorageError<Self>> {
+    ) -> Result<Vec<Epoch>, StorageError<Self>> {
         let group_id_bytes = C::to_vec(group_id)?;
         let past_group_states = self.past_group_states.read().unwrap();
-        let Some(past_group_states_bytes) = past_group_states.get(&group_id_bytes) else {
+        let Some(group_records) = past_group_states.get(&group_id_bytes) else {
+            return Ok(Vec::new());
+        };
+        group_records
+            .keys()
+            .map(|epoch_bytes| C::from_slice(epoch_bytes))
+            .collect()
+    }
+
+    fn write_past_group_state_chunk(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl serde::Serialize,
+        chunk: &impl serde::Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write_record(
+            Records::PastGroupStateChunks,
+            C::to_vec(group_id)?,
+            C::to_vec(chunk_hash)?,
+            C::to_vec(chunk)?,
+        );
+        Ok(())
+    }
+
+    fn read_past_group_state_chunk<Chunk: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl serde::Serialize,
+    ) -> Result<Option<Chunk>, StorageError<Self>> {
+        let group_id_bytes = C::to_vec(group_id)?;
+        let chunk_hash_bytes = C::to_vec(chunk_hash)?;
+        let Some(chunk_bytes) = read_record(
+            &self.past_group_state_chunks,
+            &group_id_bytes,
+            &chunk_hash_bytes,
+        ) else {
+            return Ok(None);
+        };
+        C::from_slice(&chunk_bytes).map(Some)
+    }
+
+    fn delete_past_group_state_chunk(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl serde::Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        let group_id_bytes = C::to_vec(group_id)?;
+        let chunk_hash_bytes = C::to_vec(chunk_hash)?;
+        self.delete_record(
+            Records::PastGroupStateChunks,
+            group_id_bytes,
+            chunk_hash_bytes,
+        );
+        Ok(())
+    }
+
+    fn write_accepted_messages(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        accepted_messages: &impl serde::Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        let group_id_bytes = C::to_vec(group_id)?;
+        let accepted_messages_bytes = C::to_vec(accepted_messages)?;
+        self.write_value(
+            Values::AcceptedMessages,
+            group_id_bytes,
+            accepted_messages_bytes,
+        );
+        Ok(())
+    }
+
+    fn read_accepted_messages<AcceptedMessages: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Option<AcceptedMessages>, StorageError<Self>> {
+        let group_id_bytes = C::to_vec(group_id)?;
+        let accepted_messages = self.accepted_messages.read().unwrap();
+        let Some(accepted_messages_bytes) = accepted_messages.get(&group_id_bytes) else {
             return Ok(None);
         };
-        C::from_slice(past_group_states_bytes).map(Some)
+        C::from_slice(accepted_messages_bytes).map(Some)
+    }
+
+    fn delete_accepted_messages(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), StorageError<Self>> {
+        let group_id_bytes = C::to_vec(group_id)?;
+        self.delete_value(Values::AcceptedMessages, group_id_bytes);
+        Ok(())
     }
 
     fn delete_group_info(
@@ -459,8 +902,7 @@ impl<C: Codec> MlsAssistStorageProvider for MlsAssistMemoryStorage<C> {
         group_id: &impl GroupId<CURRENT_VERSION>,
     ) -> Result<(), StorageError<Self>> {
         let group_id_bytes = C::to_vec(group_id)?;
-        let mut group_infos = self.group_infos.write().unwrap();
-        group_infos.remove(&group_id_bytes);
+        self.delete_value(Values::GroupInfos, group_id_bytes);
         Ok(())
     }
 
@@ -471,8 +913,7 @@ impl<C: Codec> MlsAssistStorageProvider for MlsAssistMemoryStorage<C> {
     ) -> Result<(), StorageError<Self>> {
         let group_id_bytes = C::to_vec(group_id)?;
         let group_info_bytes = C::to_vec(group_info)?;
-        let mut group_infos = self.group_infos.write().unwrap();
-        group_infos.insert(group_id_bytes, group_info_bytes);
+        self.write_value(Values::GroupInfos, group_id_bytes, group_info_bytes);
         Ok(())
     }
 
@@ -493,34 +934,82 @@ impl<C: Codec> MlsAssistStorageProvider for MlsAssistMemoryStorage<C> {
         group_id: &impl GroupId<CURRENT_VERSION>,
     ) -> Result<(), StorageError<Self>> {
         let group_id_bytes = C::to_vec(group_id)?;
-        let mut past_group_states = self.past_group_states.write().unwrap();
-        past_group_states.remove(&group_id_bytes);
+        self.delete_group_records(Records::PastGroupStates, group_id_bytes.clone());
+        self.delete_group_records(Records::PastGroupStateChunks, group_id_bytes);
         Ok(())
     }
 }
 
+/// A [`Clock`] that only advances when told to, e.g. to test expiration
+/// deterministically or to replay logs. Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct TestClock {
+    now: Arc<RwLock<DateTime<Utc>>>,
+}
+
+impl TestClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(RwLock::new(now)),
+        }
+    }
+
+    /// Set the current time.
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.write().unwrap() = now;
+    }
+
+    /// Advance the current time by the given duration.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.write().unwrap() += duration;
+    }
+}
+
+impl Default for TestClock {
+    /// A [`TestClock`] starting at the Unix epoch.
+    fn default() -> Self {
+        Self::new(DateTime::<Utc>::UNIX_EPOCH)
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.read().unwrap()
+    }
+}
+
 #[derive(Default)]
-pub struct MlsAssistRustCrypto<C: Codec> {
+pub struct MlsAssistRustCrypto<C: Codec, K: Clock = SystemClock> {
     crypto: RustCrypto,
     storage: MlsAssistMemoryStorage<C>,
+    clock: K,
 }
 
-impl<C: Codec> From<MlsAssistMemoryStorage<C>> for MlsAssistRustCrypto<C> {
-    fn from(storage: MlsAssistMemoryStorage<C>) -> Self {
+impl<C: Codec, K: Clock> MlsAssistRustCrypto<C, K> {
+    pub fn new(storage: MlsAssistMemoryStorage<C>, clock: K) -> Self {
         Self {
             crypto: RustCrypto::default(),
             storage,
+            clock,
         }
     }
 }
 
-impl<C: Codec> MlsAssistProvider for MlsAssistRustCrypto<C> {
+impl<C: Codec, K: Clock + Default> From<MlsAssistMemoryStorage<C>> for MlsAssistRustCrypto<C, K> {
+    fn from(storage: MlsAssistMemoryStorage<C>) -> Self {
+        Self::new(storage, K::default())
+    }
+}
+
+impl<C: Codec, K: Clock> MlsAssistProvider for MlsAssistRustCrypto<C, K> {
     type Crypto = RustCrypto;
 
     type Rand = RustCrypto;
 
     type Storage = MlsAssistMemoryStorage<C>;
 
+    type Clock = K;
+
     fn storage(&self) -> &Self::Storage {
         &self.storage
     }
@@ -532,4 +1021,97 @@ impl<C: Codec> MlsAssistProvider for MlsAssistRustCrypto<C> {
     fn rand(&self) -> &Self::Rand {
         &self.crypto
     }
+
+    fn clock(&self) -> &Self::Clock {
+        &self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use openmls::prelude::GroupId;
+    use serde_json::{Value, json};
+
+    use crate::{
+        group::{Group, past_group_states::RetentionPolicy},
+        messages::{JoinerId, PastGroupStateRequest},
+        test_utils::{Client, JsonCodec, TestGroup},
+        tls_codec::Serialize as _,
+    };
+
+    use super::*;
+
+    /// Serialize the given storage with the given past group states in the
+    /// layout of earlier versions.
+    fn with_legacy_past_group_states(
+        storage: &MlsAssistMemoryStorage<JsonCodec>,
+        group_id: &GroupId,
+        legacy_past_group_states: Value,
+    ) -> Vec<u8> {
+        let mut serialized: Value = serde_json::from_slice(&storage.serialize().unwrap()).unwrap();
+        serialized["past_group_states_bytes"] = json!([[
+            JsonCodec::to_vec(group_id).unwrap(),
+            serde_json::to_vec(&legacy_past_group_states).unwrap()
+        ]]);
+        serde_json::to_vec(&serialized).unwrap()
+    }
+
+    #[test]
+    fn legacy_past_group_states_are_migrated() {
+        let provider = MlsAssistRustCrypto::<JsonCodec>::default();
+        let group = TestGroup::new().assisted_group(&provider);
+        let group_id = group.group_info().group_context().group_id().clone();
+        let joiner = Client::new("joiner");
+        let tree = group.export_ratchet_tree();
+        let legacy_past_group_states = json!({
+            "past_group_states": {
+                group.epoch().as_u64().to_string(): {
+                    "nodes": tree,
+                    "creation_time": Utc::now(),
+                    "potential_joiners": [joiner.signature_key()],
+                }
+            }
+        });
+        let serialized =
+            with_legacy_past_group_states(provider.storage(), &group_id, legacy_past_group_states);
+
+        let storage = MlsAssistMemoryStorage::<JsonCodec>::deserialize(&serialized).unwrap();
+        let provider = MlsAssistRustCrypto::<JsonCodec>::from(storage);
+        let group = Group::load(provider.storage(), &group_id).unwrap().unwrap();
+        let request = PastGroupStateRequest::new(
+            &joiner.signer,
+            group_id,
+            group.epoch(),
+            group.epoch(),
+            JoinerId::SignatureKey(joiner.signature_key()),
+            Utc::now(),
+        )
+        .unwrap();
+        let tree_bytes = group
+            .past_group_state_bytes(
+                &provider,
+                &request,
+                &RetentionPolicy::new(Duration::days(1)),
+            )
+            .unwrap();
+        assert_eq!(tree_bytes, Some(tree.tls_serialize_detached().unwrap()));
+
+        let serialized: Value =
+            serde_json::from_slice(&provider.storage().serialize().unwrap()).unwrap();
+        assert!(serialized.get("past_group_states_bytes").is_none());
+    }
+
+    #[test]
+    fn undecodable_legacy_past_group_states_are_an_error() {
+        let provider = MlsAssistRustCrypto::<JsonCodec>::default();
+        let group = TestGroup::new().assisted_group(&provider);
+        let group_id = group.group_info().group_context().group_id().clone();
+        let legacy_past_group_states = json!({
+            "past_group_states": { "1": { "chunks": [] } }
+        });
+        let serialized =
+            with_legacy_past_group_states(provider.storage(), &group_id, legacy_past_group_states);
+
+        assert!(MlsAssistMemoryStorage::<JsonCodec>::deserialize(&serialized).is_err());
+    }
 }
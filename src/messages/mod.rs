@@ -2,15 +2,24 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use openmls::prelude::tls_codec::{self, TlsDeserialize, TlsSerialize, TlsSize};
+use chrono::{DateTime, Utc};
+use openmls::prelude::tls_codec::{
+    self, Serialize as _, TlsDeserialize, TlsSerialize, TlsSize, VLBytes,
+};
 use openmls::{
     framing::{ContentType, MlsMessageBodyOut},
     prelude::{
-        ConfirmationTag, Extensions, GroupContext, GroupId, KeyPackageRef, LeafNodeIndex,
-        MlsMessageOut, ProtocolMessage, Sender, Signature, Welcome,
-        group_info::VerifiableGroupInfo,
+        ConfirmationTag, Extensions, GroupContext, GroupEpoch, GroupId, KeyPackageRef,
+        LeafNodeIndex, MlsMessageOut, ProtocolMessage, Sender, Signature, SignaturePublicKey,
+        Welcome, group_info::VerifiableGroupInfo,
     },
 };
+use openmls_traits::{
+    crypto::OpenMlsCrypto,
+    signatures::{Signer, SignerError},
+    types::SignatureScheme,
+};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[cfg(doc)]
@@ -26,6 +35,14 @@ pub enum AssistedMessageError {
     MissingGroupInfo,
 }
 
+#[derive(Debug, Error)]
+pub enum CreatePastGroupStateRequestError {
+    #[error("Error encoding the request: {0}")]
+    CodecError(#[from] tls_codec::Error),
+    #[error("Error signing the request: {0:?}")]
+    SignerError(SignerError),
+}
+
 #[derive(Debug, TlsSerialize, TlsSize)]
 pub struct AssistedMessageOut {
     mls_message: MlsMessageOut,
@@ -69,14 +86,14 @@ impl AssistedMessageOut {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AssistedMessageIn {
     pub(crate) mls_message: ProtocolMessage,
     pub(crate) serialized_mls_message: SerializedMlsMessage,
     pub(crate) group_info_option: Option<AssistedGroupInfoIn>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SerializedMlsMessage(pub Vec<u8>);
 
 impl AssistedMessageIn {
@@ -138,3 +155,139 @@ impl AssistedWelcome {
             .map(|secret| secret.new_member())
     }
 }
+
+/// Label of the signature over a [`PastGroupStateRequest`].
+const PAST_GROUP_STATE_REQUEST_LABEL: &[u8] = b"MLS 1.0 MLS-Assist PastGroupStateRequest";
+
+#[derive(TlsSerialize, TlsSize)]
+struct SignContent {
+    label: VLBytes,
+    content: VLBytes,
+}
+
+/// Identifies the joiner requesting a past group state.
+#[derive(Debug, Clone, PartialEq, Eq, TlsSerialize, TlsDeserialize, TlsSize)]
+#[repr(u8)]
+pub enum JoinerId {
+    /// The signature key of the joiner's leaf.
+    #[tls_codec(discriminant = 1)]
+    SignatureKey(SignaturePublicKey),
+    /// The ref of the key package the joiner was added with, as listed in
+    /// the Welcome (see [`AssistedWelcome::joiners`]).
+    #[tls_codec(discriminant = 2)]
+    KeyPackageRef(KeyPackageRef),
+}
+
+#[derive(Debug, Clone, TlsSerialize, TlsDeserialize, TlsSize)]
+struct PastGroupStateRequestTbs {
+    group_id: GroupId,
+    epoch: GroupEpoch,
+    /// The epoch the group is in when the request is made.
+    current_epoch: GroupEpoch,
+    joiner: JoinerId,
+    /// Seconds since the Unix epoch.
+    timestamp: u64,
+}
+
+impl PastGroupStateRequestTbs {
+    /// Returns the bytes the signature is computed over.
+    fn sign_content(&self) -> Result<Vec<u8>, tls_codec::Error> {
+        SignContent {
+            label: PAST_GROUP_STATE_REQUEST_LABEL.into(),
+            content: self.tls_serialize_detached()?.into(),
+        }
+        .tls_serialize_detached()
+    }
+}
+
+/// A request by a joiner for the past group state of an epoch. The request
+/// is signed with the joiner's signature key to prove possession of the
+/// corresponding private key. It includes the group's current epoch and a
+/// timestamp to prevent it from being replayed later.
+///
+/// The request carries no nonce, so it can be replayed as long as its
+/// timestamp is within the request validity and the group hasn't moved on to
+/// another epoch. This is accepted, since a replayed request only yields the
+/// tree the joiner was already entitled to, and serving it doesn't change any
+/// state. A nonce would require the server to remember the nonces of all
+/// requests it served within the validity window.
+#[derive(Debug, Clone, TlsSerialize, TlsDeserialize, TlsSize)]
+pub struct PastGroupStateRequest {
+    tbs: PastGroupStateRequestTbs,
+    signature: VLBytes,
+}
+
+impl PastGroupStateRequest {
+    /// Create a new [`PastGroupStateRequest`] for the past group state of the
+    /// given group and epoch, signed by `signer`. `current_epoch` is the epoch
+    /// the group is in now, as learned from the DS. `joiner` must identify
+    /// the signer's leaf.
+    pub fn new(
+        signer: &impl Signer,
+        group_id: GroupId,
+        epoch: GroupEpoch,
+        current_epoch: GroupEpoch,
+        joiner: JoinerId,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Self, CreatePastGroupStateRequestError> {
+        let tbs = PastGroupStateRequestTbs {
+            group_id,
+            epoch,
+            current_epoch,
+            joiner,
+            // Timestamps before the Unix epoch are never fresh.
+            timestamp: u64::try_from(timestamp.timestamp()).unwrap_or_default(),
+        };
+        let signature = signer
+            .sign(&tbs.sign_content()?)
+            .map_err(CreatePastGroupStateRequestError::SignerError)?;
+        Ok(Self {
+            tbs,
+            signature: signature.into(),
+        })
+    }
+
+    pub fn group_id(&self) -> &GroupId {
+        &self.tbs.group_id
+    }
+
+    pub fn epoch(&self) -> GroupEpoch {
+        self.tbs.epoch
+    }
+
+    pub fn current_epoch(&self) -> GroupEpoch {
+        self.tbs.current_epoch
+    }
+
+    pub fn joiner(&self) -> &JoinerId {
+        &self.tbs.joiner
+    }
+
+    /// Returns the time at which the request was created or `None` if it
+    /// can't be represented.
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        let seconds = i64::try_from(self.tbs.timestamp).ok()?;
+        DateTime::from_timestamp(seconds, 0)
+    }
+
+    /// Returns true if the request is signed by the private key corresponding
+    /// to the joiner's signature key.
+    pub(crate) fn verify_signature(
+        &self,
+        crypto: &impl OpenMlsCrypto,
+        signature_scheme: SignatureScheme,
+        joiner: &SignaturePublicKey,
+    ) -> bool {
+        let Ok(sign_content) = self.tbs.sign_content() else {
+            return false;
+        };
+        crypto
+            .verify_signature(
+                signature_scheme,
+                &sign_content,
+                joiner.as_slice(),
+                self.signature.as_slice(),
+            )
+            .is_ok()
+    }
+}
@@ -2,6 +2,7 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use chrono::{DateTime, Utc};
 use openmls::storage::PublicStorageProvider;
 use openmls_traits::{
     crypto::OpenMlsCrypto,
@@ -13,22 +14,89 @@ use serde::{Serialize, de::DeserializeOwned};
 use crate::group::errors::StorageError;
 
 pub trait MlsAssistStorageProvider: PublicStorageProvider {
-    fn write_past_group_states(
+    /// Run `f` as a transaction. If `f` returns an error, all writes made by
+    /// it are rolled back, otherwise they are committed. The outer error is
+    /// returned if the transaction itself can't be started, committed or
+    /// rolled back.
+    fn with_transaction<T, E>(
+        &self,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Result<T, E>, StorageError<Self>>;
+
+    /// Returns the ids of all groups for which any MLS-assist specific state
+    /// is stored.
+    fn group_ids<GroupId: DeserializeOwned>(&self) -> Result<Vec<GroupId>, StorageError<Self>>;
+
+    /// Write the past group state of the given epoch.
+    fn write_past_group_state(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl Serialize,
+        past_group_state: &impl Serialize,
+    ) -> Result<(), StorageError<Self>>;
+
+    fn read_past_group_state<PastGroupState: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl Serialize,
+    ) -> Result<Option<PastGroupState>, StorageError<Self>>;
+
+    fn delete_past_group_state(
         &self,
         group_id: &impl GroupId<CURRENT_VERSION>,
-        past_group_states: &impl Serialize,
+        epoch: &impl Serialize,
     ) -> Result<(), StorageError<Self>>;
 
-    fn read_past_group_states<PastGroupStates: DeserializeOwned>(
+    /// Returns the epochs for which past group states of the given group are
+    /// stored.
+    fn past_group_state_epochs<Epoch: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Vec<Epoch>, StorageError<Self>>;
+
+    /// Write a chunk of the trees of the past group states of the given group.
+    /// Chunks are shared between the past group states of a group.
+    fn write_past_group_state_chunk(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl Serialize,
+        chunk: &impl Serialize,
+    ) -> Result<(), StorageError<Self>>;
+
+    fn read_past_group_state_chunk<Chunk: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl Serialize,
+    ) -> Result<Option<Chunk>, StorageError<Self>>;
+
+    fn delete_past_group_state_chunk(
         &self,
         group_id: &impl GroupId<CURRENT_VERSION>,
-    ) -> Result<Option<PastGroupStates>, StorageError<Self>>;
+        chunk_hash: &impl Serialize,
+    ) -> Result<(), StorageError<Self>>;
 
+    /// Delete all past group states and chunks of the given group.
     fn delete_past_group_states(
         &self,
         group_id: &impl GroupId<CURRENT_VERSION>,
     ) -> Result<(), StorageError<Self>>;
 
+    fn write_accepted_messages(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        accepted_messages: &impl Serialize,
+    ) -> Result<(), StorageError<Self>>;
+
+    fn read_accepted_messages<AcceptedMessages: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Option<AcceptedMessages>, StorageError<Self>>;
+
+    fn delete_accepted_messages(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), StorageError<Self>>;
+
     fn write_group_info(
         &self,
         group_id: &impl GroupId<CURRENT_VERSION>,
@@ -46,15 +114,34 @@ pub trait MlsAssistStorageProvider: PublicStorageProvider {
     ) -> Result<(), StorageError<Self>>;
 }
 
+/// A source of the current time. All expiration logic of MLS-assist gets the
+/// time from here.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// A [`Clock`] that returns the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
 /// A storage provider for MLS-assist.
 pub trait MlsAssistProvider {
     type Storage: MlsAssistStorageProvider;
     type Crypto: OpenMlsCrypto;
     type Rand: OpenMlsRand;
+    type Clock: Clock;
 
     fn storage(&self) -> &Self::Storage;
 
     fn crypto(&self) -> &Self::Crypto;
 
     fn rand(&self) -> &Self::Rand;
+
+    fn clock(&self) -> &Self::Clock;
 }
@@ -0,0 +1,432 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! A storage provider that persists all state in a redb database.
+//!
+//! [`RedbStore`] is a [`KeyValueStore`] that keeps each kind of record in its
+//! own table, using the namespace byte of the [key
+//! layout](crate::kv_provider) to pick the table. [`MlsAssistRedbStorage`]
+//! puts the [`KvStorage`] adapter on top of it, which commits each storage
+//! transaction, such as accepting a message, with a single redb write
+//! transaction.
+
+use std::path::Path;
+
+use openmls_rust_crypto::RustCrypto;
+use redb::{Database, TableDefinition, backends::InMemoryBackend};
+use thiserror::Error;
+
+use crate::{
+    kv_provider::{KeyValueStore, KvEntries, KvOperation, KvStorage, NAMESPACES, Namespace},
+    memory_provider::Codec,
+    provider_traits::{Clock, MlsAssistProvider, SystemClock},
+};
+
+type Table = TableDefinition<'static, &'static [u8], &'static [u8]>;
+
+const TREES: Table = TableDefinition::new("trees");
+const CONTEXTS: Table = TableDefinition::new("contexts");
+const INTERIM_TRANSCRIPT_HASHES: Table = TableDefinition::new("interim_transcript_hashes");
+const CONFIRMATION_TAGS: Table = TableDefinition::new("confirmation_tags");
+const PROPOSALS: Table = TableDefinition::new("proposals");
+const GROUP_INFOS: Table = TableDefinition::new("group_infos");
+const ACCEPTED_MESSAGES: Table = TableDefinition::new("accepted_messages");
+const PAST_GROUP_STATES: Table = TableDefinition::new("past_group_states");
+const PAST_GROUP_STATE_CHUNKS: Table = TableDefinition::new("past_group_state_chunks");
+
+impl Namespace {
+    fn from_byte(byte: u8) -> Option<Self> {
+        NAMESPACES
+            .into_iter()
+            .find(|namespace| *namespace as u8 == byte)
+    }
+
+    fn table(self) -> Table {
+        match self {
+            Namespace::Tree => TREES,
+            Namespace::Context => CONTEXTS,
+            Namespace::InterimTranscriptHash => INTERIM_TRANSCRIPT_HASHES,
+            Namespace::ConfirmationTag => CONFIRMATION_TAGS,
+            Namespace::Proposal => PROPOSALS,
+            Namespace::GroupInfo => GROUP_INFOS,
+            Namespace::AcceptedMessages => ACCEPTED_MESSAGES,
+            Namespace::PastGroupState => PAST_GROUP_STATES,
+            Namespace::PastGroupStateChunk => PAST_GROUP_STATE_CHUNKS,
+        }
+    }
+}
+
+/// Error returned by [`RedbStore`].
+#[derive(Debug, Error)]
+pub enum RedbStoreError {
+    /// Error accessing the database. Boxed, since it is large.
+    #[error(transparent)]
+    Redb(Box<redb::Error>),
+    /// The key doesn't start with a known namespace.
+    #[error("Key without a known namespace.")]
+    UnknownNamespace,
+}
+
+macro_rules! impl_from_redb_errors {
+    ($($error:ty),*) => {
+        $(
+            impl From<$error> for RedbStoreError {
+                fn from(error: $error) -> Self {
+                    Self::Redb(Box::new(error.into()))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_redb_errors!(
+    redb::Error,
+    redb::DatabaseError,
+    redb::TransactionError,
+    redb::TableError,
+    redb::StorageError,
+    redb::CommitError
+);
+
+/// Returns the namespace of the given key and the key within the
+/// namespace's table.
+fn split_key(key: &[u8]) -> Result<(Namespace, &[u8]), RedbStoreError> {
+    let (namespace, table_key) = key.split_first().ok_or(RedbStoreError::UnknownNamespace)?;
+    let namespace = Namespace::from_byte(*namespace).ok_or(RedbStoreError::UnknownNamespace)?;
+    Ok((namespace, table_key))
+}
+
+/// A write of the value of a key in the table of a namespace, or its
+/// deletion if the value is `None`.
+type Operation<'a> = (Namespace, &'a [u8], Option<&'a [u8]>);
+
+/// A [`KeyValueStore`] backed by a redb database.
+pub struct RedbStore {
+    database: Database,
+}
+
+impl RedbStore {
+    /// Open the database at the given path, creating it if it doesn't exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RedbStoreError> {
+        Self::from_database(Database::create(path)?)
+    }
+
+    /// Open a new in-memory database.
+    pub fn open_in_memory() -> Result<Self, RedbStoreError> {
+        Self::from_database(Database::builder().create_with_backend(InMemoryBackend::new())?)
+    }
+
+    /// Use the given database, creating the tables that don't exist yet.
+    pub fn from_database(database: Database) -> Result<Self, RedbStoreError> {
+        let transaction = database.begin_write()?;
+        for namespace in NAMESPACES {
+            transaction.open_table(namespace.table())?;
+        }
+        transaction.commit()?;
+        Ok(Self { database })
+    }
+
+    fn read(&self, table: Table, key: &[u8]) -> Result<Option<Vec<u8>>, RedbStoreError> {
+        let transaction = self.database.begin_read()?;
+        let table = transaction.open_table(table)?;
+        Ok(table.get(key)?.map(|value| value.value().to_vec()))
+    }
+
+    /// Returns `entry` of the key and value of all entries of the given table
+    /// whose key starts with the given prefix, with the namespace byte
+    /// prepended to their keys.
+    fn scan<T>(
+        &self,
+        namespace: Namespace,
+        prefix: &[u8],
+        entry: &mut impl FnMut(Vec<u8>, &[u8]) -> T,
+    ) -> Result<Vec<T>, RedbStoreError> {
+        let transaction = self.database.begin_read()?;
+        let table = transaction.open_table(namespace.table())?;
+        let mut entries = Vec::new();
+        for range_entry in table.range(prefix..)? {
+            let (key, value) = range_entry?;
+            let key = key.value();
+            if !key.starts_with(prefix) {
+                break;
+            }
+            let mut full_key = Vec::with_capacity(1 + key.len());
+            full_key.push(namespace as u8);
+            full_key.extend_from_slice(key);
+            entries.push(entry(full_key, value.value()));
+        }
+        Ok(entries)
+    }
+
+    /// Returns `entry` of the key and value of all entries whose key starts
+    /// with the given prefix. An empty prefix matches the entries of all
+    /// tables.
+    fn scan_prefix_with<T>(
+        &self,
+        prefix: &[u8],
+        mut entry: impl FnMut(Vec<u8>, &[u8]) -> T,
+    ) -> Result<Vec<T>, RedbStoreError> {
+        if prefix.is_empty() {
+            let mut entries = Vec::new();
+            for namespace in NAMESPACES {
+                entries.extend(self.scan(namespace, &[], &mut entry)?);
+            }
+            return Ok(entries);
+        }
+        let (namespace, prefix) = split_key(prefix)?;
+        self.scan(namespace, prefix, &mut entry)
+    }
+
+    /// Apply the given operations in a single write transaction.
+    fn write(&self, operations: &[Operation<'_>]) -> Result<(), RedbStoreError> {
+        let transaction = self.database.begin_write()?;
+        for (namespace, key, value) in operations {
+            let mut table = transaction.open_table(namespace.table())?;
+            match value {
+                Some(value) => table.insert(*key, *value)?,
+                None => table.remove(*key)?,
+            };
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+impl KeyValueStore for RedbStore {
+    type Error = RedbStoreError;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        let (namespace, key) = split_key(key)?;
+        self.read(namespace.table(), key)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), Self::Error> {
+        let (namespace, key) = split_key(key)?;
+        self.write(&[(namespace, key, Some(value))])
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<(), Self::Error> {
+        let (namespace, key) = split_key(key)?;
+        self.write(&[(namespace, key, None)])
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<KvEntries, Self::Error> {
+        self.scan_prefix_with(prefix, |key, value| (key, value.to_vec()))
+    }
+
+    /// Returns the keys without copying the values out of the database.
+    fn scan_prefix_keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error> {
+        self.scan_prefix_with(prefix, |key, _| key)
+    }
+
+    /// Applies all operations in a single write transaction, so either all
+    /// of them are persisted or none.
+    fn batch(&self, operations: Vec<KvOperation>) -> Result<(), Self::Error> {
+        let operations = operations
+            .iter()
+            .map(|operation| match operation {
+                KvOperation::Put { key, value } => {
+                    let (namespace, key) = split_key(key)?;
+                    Ok((namespace, key, Some(value.as_slice())))
+                }
+                KvOperation::Delete { key } => {
+                    let (namespace, key) = split_key(key)?;
+                    Ok((namespace, key, None))
+                }
+            })
+            .collect::<Result<Vec<_>, RedbStoreError>>()?;
+        self.write(&operations)
+    }
+}
+
+/// An [`MlsAssistStorageProvider`](crate::provider_traits::MlsAssistStorageProvider)
+/// that stores all state in a redb database. Values are encoded with the
+/// codec `C`.
+pub type MlsAssistRedbStorage<C> = KvStorage<RedbStore, C>;
+
+/// An [`MlsAssistProvider`] using [`RustCrypto`] and an
+/// [`MlsAssistRedbStorage`].
+pub struct MlsAssistRedbRustCrypto<C: Codec, K: Clock = SystemClock> {
+    crypto: RustCrypto,
+    storage: MlsAssistRedbStorage<C>,
+    clock: K,
+}
+
+impl<C: Codec, K: Clock> MlsAssistRedbRustCrypto<C, K> {
+    /// Create a provider on top of the given storage that takes the current
+    /// time from `clock`.
+    pub fn new(storage: MlsAssistRedbStorage<C>, clock: K) -> Self {
+        Self {
+            crypto: RustCrypto::default(),
+            storage,
+            clock,
+        }
+    }
+}
+
+impl<C: Codec, K: Clock + Default> From<MlsAssistRedbStorage<C>> for MlsAssistRedbRustCrypto<C, K> {
+    fn from(storage: MlsAssistRedbStorage<C>) -> Self {
+        Self::new(storage, K::default())
+    }
+}
+
+impl<C: Codec, K: Clock> MlsAssistProvider for MlsAssistRedbRustCrypto<C, K> {
+    type Crypto = RustCrypto;
+
+    type Rand = RustCrypto;
+
+    type Storage = MlsAssistRedbStorage<C>;
+
+    type Clock = K;
+
+    fn storage(&self) -> &Self::Storage {
+        &self.storage
+    }
+
+    fn crypto(&self) -> &Self::Crypto {
+        &self.crypto
+    }
+
+    fn rand(&self) -> &Self::Rand {
+        &self.crypto
+    }
+
+    fn clock(&self) -> &Self::Clock {
+        &self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        panic::{self, AssertUnwindSafe},
+        path::PathBuf,
+        process,
+    };
+
+    use openmls::prelude::GroupId;
+
+    use crate::{provider_traits::MlsAssistStorageProvider, test_utils::JsonCodec};
+
+    use super::*;
+
+    /// A database file that is removed when dropped.
+    struct DatabaseFile(PathBuf);
+
+    impl DatabaseFile {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("mls-assist-{name}-{}.redb", process::id()));
+            let _ = std::fs::remove_file(&path);
+            Self(path)
+        }
+
+        fn open(&self) -> MlsAssistRedbStorage<JsonCodec> {
+            KvStorage::new(RedbStore::open(&self.0).unwrap())
+        }
+    }
+
+    impl Drop for DatabaseFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn group_info(storage: &MlsAssistRedbStorage<JsonCodec>, group_id: &GroupId) -> Option<String> {
+        storage.read_group_info(group_id).unwrap()
+    }
+
+    #[test]
+    fn committed_transactions_survive_reopening() {
+        let file = DatabaseFile::new("committed");
+        let group_id = GroupId::from_slice(&[1]);
+        let storage = file.open();
+        storage
+            .with_transaction(|| {
+                storage.write_group_info(&group_id, &"committed")?;
+                storage.write_past_group_state(&group_id, &1u64, &"committed")
+            })
+            .unwrap()
+            .unwrap();
+        drop(storage);
+
+        let storage = file.open();
+        assert_eq!(
+            group_info(&storage, &group_id).as_deref(),
+            Some("committed")
+        );
+        assert_eq!(
+            storage.past_group_state_epochs::<u64>(&group_id).unwrap(),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn aborted_transactions_are_not_persisted() {
+        let file = DatabaseFile::new("aborted");
+        let group_id = GroupId::from_slice(&[1]);
+        let storage = file.open();
+        storage.write_group_info(&group_id, &"before").unwrap();
+
+        let result = storage
+            .with_transaction(|| {
+                storage.write_group_info(&group_id, &"failed").unwrap();
+                storage
+                    .write_past_group_state(&group_id, &1u64, &"failed")
+                    .unwrap();
+                Err::<(), _>(())
+            })
+            .unwrap();
+        assert!(result.is_err());
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| {
+            storage.with_transaction(|| -> Result<(), ()> {
+                storage.write_group_info(&group_id, &"panicked").unwrap();
+                storage
+                    .write_past_group_state(&group_id, &2u64, &"panicked")
+                    .unwrap();
+                panic!("crashed during a transaction");
+            })
+        }));
+        assert!(panicked.is_err());
+        drop(storage);
+
+        let storage = file.open();
+        assert_eq!(group_info(&storage, &group_id).as_deref(), Some("before"));
+        assert!(
+            storage
+                .past_group_state_epochs::<u64>(&group_id)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn interrupted_commits_are_not_persisted() {
+        let file = DatabaseFile::new("interrupted");
+        let group_id = GroupId::from_slice(&[1]);
+        let storage = file.open();
+        storage.write_group_info(&group_id, &"before").unwrap();
+        let group_info_key = storage
+            .store()
+            .scan_prefix(&[Namespace::GroupInfo as u8])
+            .unwrap()
+            .remove(0)
+            .0;
+
+        // Write part of a batch and stop before committing it, as if the
+        // process crashed.
+        let transaction = storage.store().database.begin_write().unwrap();
+        {
+            let mut table = transaction.open_table(GROUP_INFOS).unwrap();
+            table
+                .insert(&group_info_key[1..], b"\"interrupted\"".as_slice())
+                .unwrap();
+        }
+        drop(transaction);
+        drop(storage);
+
+        let storage = file.open();
+        assert_eq!(group_info(&storage, &group_id).as_deref(), Some("before"));
+    }
+}
@@ -0,0 +1,926 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! A storage provider that persists all state in an SQLite database.
+
+use std::{
+    marker::PhantomData,
+    ops::Deref,
+    path::Path,
+    sync::{Mutex, MutexGuard},
+};
+
+use openmls_rust_crypto::RustCrypto;
+use openmls_traits::{
+    public_storage::PublicStorageProvider,
+    storage::{
+        CURRENT_VERSION, Entity,
+        traits::{self, GroupId},
+    },
+};
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+use crate::{
+    group::errors::StorageError,
+    memory_provider::Codec,
+    provider_traits::{Clock, MlsAssistProvider, MlsAssistStorageProvider, SystemClock},
+    transaction_lock::{TransactionGuard, TransactionLock},
+};
+
+/// Schema migrations. The migration at index `i` migrates the schema from
+/// version `i` to version `i + 1`. The current version is stored in the
+/// database's `user_version`.
+const MIGRATIONS: &[&str] = &[
+    // Version 1
+    "CREATE TABLE public_groups (
+        group_id BLOB PRIMARY KEY,
+        tree BLOB,
+        context BLOB,
+        interim_transcript_hash BLOB,
+        confirmation_tag BLOB
+    );
+    CREATE TABLE proposals (
+        group_id BLOB NOT NULL,
+        proposal_ref BLOB NOT NULL,
+        proposal BLOB NOT NULL,
+        PRIMARY KEY (group_id, proposal_ref)
+    );
+    CREATE TABLE group_infos (
+        group_id BLOB PRIMARY KEY,
+        group_info BLOB NOT NULL
+    );
+    CREATE TABLE accepted_messages (
+        group_id BLOB PRIMARY KEY,
+        accepted_messages BLOB NOT NULL
+    );
+    CREATE TABLE past_group_states (
+        group_id BLOB NOT NULL,
+        epoch BLOB NOT NULL,
+        past_group_state BLOB NOT NULL,
+        PRIMARY KEY (group_id, epoch)
+    );
+    CREATE TABLE past_group_state_chunks (
+        group_id BLOB NOT NULL,
+        chunk_hash BLOB NOT NULL,
+        chunk BLOB NOT NULL,
+        PRIMARY KEY (group_id, chunk_hash)
+    );",
+];
+
+/// Error returned when opening an [`MlsAssistSqliteStorage`].
+#[derive(Debug, Error)]
+pub enum SqliteOpenError {
+    /// Error accessing the database.
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    /// The database was migrated to a schema version this version of the
+    /// crate doesn't know, e.g. by a newer version.
+    #[error("Unsupported schema version {version}, the latest known version is {latest}.")]
+    UnsupportedVersion {
+        /// The schema version of the database.
+        version: usize,
+        /// The latest schema version known to this version of the crate.
+        latest: usize,
+    },
+}
+
+/// Error returned by [`MlsAssistSqliteStorage`].
+#[derive(Debug, Error)]
+pub enum SqliteStorageError<E: std::error::Error> {
+    /// Error accessing the database.
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    /// Error encoding or decoding a value.
+    #[error(transparent)]
+    Codec(E),
+}
+
+/// The columns of the `public_groups` table that hold the state of a
+/// [`PublicGroup`](openmls::prelude::PublicGroup).
+#[derive(Clone, Copy)]
+enum DataType {
+    TreeSync,
+    InterimTranscriptHash,
+    Context,
+    ConfirmationTag,
+}
+
+impl DataType {
+    fn column(self) -> &'static str {
+        match self {
+            DataType::TreeSync => "tree",
+            DataType::InterimTranscriptHash => "interim_transcript_hash",
+            DataType::Context => "context",
+            DataType::ConfirmationTag => "confirmation_tag",
+        }
+    }
+}
+
+/// The connection of an [`MlsAssistSqliteStorage`], locked for the current
+/// thread.
+struct LockedConnection<'a> {
+    connection: MutexGuard<'a, Connection>,
+    _transaction: TransactionGuard<'a>,
+}
+
+impl Deref for LockedConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.connection
+    }
+}
+
+/// An [`MlsAssistStorageProvider`] that stores all state in an SQLite
+/// database. Values are encoded with the codec `C`.
+pub struct MlsAssistSqliteStorage<C: Codec> {
+    connection: Mutex<Connection>,
+    transaction_lock: TransactionLock,
+    _codec: PhantomData<C>,
+}
+
+impl<C: Codec> MlsAssistSqliteStorage<C> {
+    /// Open the database at the given path, creating it if it doesn't exist,
+    /// and migrate it to the current schema.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SqliteOpenError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Open a new in-memory database.
+    pub fn open_in_memory() -> Result<Self, SqliteOpenError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// Use the given connection, migrating its database to the current
+    /// schema. Databases with a newer schema are refused.
+    pub fn from_connection(mut connection: Connection) -> Result<Self, SqliteOpenError> {
+        migrate(&mut connection)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            transaction_lock: TransactionLock::default(),
+            _codec: PhantomData,
+        })
+    }
+
+    /// Lock the connection, waiting for transactions of other threads to end.
+    fn connection(&self) -> LockedConnection<'_> {
+        let transaction = self.transaction_lock.enter();
+        LockedConnection {
+            connection: self.connection.lock().unwrap(),
+            _transaction: transaction,
+        }
+    }
+
+    fn encode(value: &impl Serialize) -> Result<Vec<u8>, SqliteStorageError<C::Error>> {
+        C::to_vec(value).map_err(SqliteStorageError::Codec)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, SqliteStorageError<C::Error>> {
+        C::from_slice(bytes).map_err(SqliteStorageError::Codec)
+    }
+
+    fn write_payload<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        Payload: Entity<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        payload: &Payload,
+        data_type: DataType,
+    ) -> Result<(), SqliteStorageError<C::Error>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let payload_bytes = Self::encode(payload)?;
+        let column = data_type.column();
+        self.connection().execute(
+            &format!(
+                "INSERT INTO public_groups (group_id, {column}) VALUES (?1, ?2)
+                ON CONFLICT (group_id) DO UPDATE SET {column} = excluded.{column}"
+            ),
+            params![group_id_bytes, payload_bytes],
+        )?;
+        Ok(())
+    }
+
+    fn read_payload<GroupId: traits::GroupId<CURRENT_VERSION>, Payload: Entity<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        data_type: DataType,
+    ) -> Result<Option<Payload>, SqliteStorageError<C::Error>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let column = data_type.column();
+        let payload_bytes = self
+            .connection()
+            .query_row(
+                &format!("SELECT {column} FROM public_groups WHERE group_id = ?1"),
+                params![group_id_bytes],
+                |row| row.get::<_, Option<Vec<u8>>>(0),
+            )
+            .optional()?
+            .flatten();
+        payload_bytes
+            .map(|payload_bytes| Self::decode(&payload_bytes))
+            .transpose()
+    }
+
+    fn delete_payload<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+        data_type: DataType,
+    ) -> Result<(), SqliteStorageError<C::Error>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let column = data_type.column();
+        let connection = self.connection();
+        connection.execute(
+            &format!("UPDATE public_groups SET {column} = NULL WHERE group_id = ?1"),
+            params![group_id_bytes],
+        )?;
+        connection.execute(
+            "DELETE FROM public_groups WHERE group_id = ?1
+                AND tree IS NULL
+                AND context IS NULL
+                AND interim_transcript_hash IS NULL
+                AND confirmation_tag IS NULL",
+            params![group_id_bytes],
+        )?;
+        Ok(())
+    }
+
+    /// Write the value of a table with one value per group.
+    fn write_group_value(
+        &self,
+        table: &str,
+        column: &str,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        value: &impl Serialize,
+    ) -> Result<(), SqliteStorageError<C::Error>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let value_bytes = Self::encode(value)?;
+        self.connection().execute(
+            &format!("INSERT OR REPLACE INTO {table} (group_id, {column}) VALUES (?1, ?2)"),
+            params![group_id_bytes, value_bytes],
+        )?;
+        Ok(())
+    }
+
+    /// Read the value of a table with one value per group.
+    fn read_group_value<T: DeserializeOwned>(
+        &self,
+        table: &str,
+        column: &str,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Option<T>, SqliteStorageError<C::Error>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let value_bytes = self
+            .connection()
+            .query_row(
+                &format!("SELECT {column} FROM {table} WHERE group_id = ?1"),
+                params![group_id_bytes],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?;
+        value_bytes
+            .map(|value_bytes| Self::decode(&value_bytes))
+            .transpose()
+    }
+
+    /// Delete all rows of the given group from a table.
+    fn delete_group_rows(
+        &self,
+        table: &str,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), SqliteStorageError<C::Error>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        self.connection().execute(
+            &format!("DELETE FROM {table} WHERE group_id = ?1"),
+            params![group_id_bytes],
+        )?;
+        Ok(())
+    }
+}
+
+/// Apply all migrations that haven't been applied to the database yet.
+fn migrate(connection: &mut Connection) -> Result<(), SqliteOpenError> {
+    apply_migrations(connection, MIGRATIONS)
+}
+
+/// Apply the given migrations in order, skipping the ones the database's
+/// `user_version` says were already applied. Each migration runs in its own
+/// transaction, together with the update of the version. A database whose
+/// version is beyond the given migrations is refused, since its schema is
+/// unknown.
+fn apply_migrations(
+    connection: &mut Connection,
+    migrations: &[&str],
+) -> Result<(), SqliteOpenError> {
+    let version: usize = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version > migrations.len() {
+        return Err(SqliteOpenError::UnsupportedVersion {
+            version,
+            latest: migrations.len(),
+        });
+    }
+    for (index, migration) in migrations.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", index + 1)?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+impl<C: Codec> PublicStorageProvider<CURRENT_VERSION> for MlsAssistSqliteStorage<C> {
+    /// An opaque error returned by all methods on this trait.
+    type PublicError = SqliteStorageError<C::Error>;
+
+    /// Write the TreeSync tree.
+    fn write_tree<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        TreeSync: traits::TreeSync<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        tree: &TreeSync,
+    ) -> Result<(), Self::PublicError> {
+        self.write_payload(group_id, tree, DataType::TreeSync)
+    }
+
+    /// Write the interim transcript hash.
+    fn write_interim_transcript_hash<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        InterimTranscriptHash: traits::InterimTranscriptHash<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        interim_transcript_hash: &InterimTranscriptHash,
+    ) -> Result<(), Self::PublicError> {
+        self.write_payload(
+            group_id,
+            interim_transcript_hash,
+            DataType::InterimTranscriptHash,
+        )
+    }
+
+    /// Write the group context.
+    fn write_context<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        GroupContext: traits::GroupContext<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        group_context: &GroupContext,
+    ) -> Result<(), Self::PublicError> {
+        self.write_payload(group_id, group_context, DataType::Context)
+    }
+
+    /// Write the confirmation tag.
+    fn write_confirmation_tag<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ConfirmationTag: traits::ConfirmationTag<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        confirmation_tag: &ConfirmationTag,
+    ) -> Result<(), Self::PublicError> {
+        self.write_payload(group_id, confirmation_tag, DataType::ConfirmationTag)
+    }
+
+    /// Enqueue a proposal.
+    fn queue_proposal<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+        QueuedProposal: traits::QueuedProposal<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+        proposal: &QueuedProposal,
+    ) -> Result<(), Self::PublicError> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let proposal_ref_bytes = Self::encode(proposal_ref)?;
+        let proposal_bytes = Self::encode(proposal)?;
+        self.connection().execute(
+            "INSERT OR REPLACE INTO proposals (group_id, proposal_ref, proposal)
+                VALUES (?1, ?2, ?3)",
+            params![group_id_bytes, proposal_ref_bytes, proposal_bytes],
+        )?;
+        Ok(())
+    }
+
+    /// Returns all queued proposals for the group with group id `group_id`, or an empty vector of none are stored.
+    fn queued_proposals<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+        QueuedProposal: traits::QueuedProposal<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Vec<(ProposalRef, QueuedProposal)>, Self::PublicError> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT proposal_ref, proposal FROM proposals
+                WHERE group_id = ?1 ORDER BY proposal_ref",
+        )?;
+        let rows = statement
+            .query_map(params![group_id_bytes], |row| {
+                Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.iter()
+            .map(|(proposal_ref_bytes, proposal_bytes)| {
+                Ok((
+                    Self::decode(proposal_ref_bytes)?,
+                    Self::decode(proposal_bytes)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Returns the TreeSync tree for the group with group id `group_id`.
+    fn tree<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        TreeSync: traits::TreeSync<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<TreeSync>, Self::PublicError> {
+        self.read_payload(group_id, DataType::TreeSync)
+    }
+
+    /// Returns the group context for the group with group id `group_id`.
+    fn group_context<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        GroupContext: traits::GroupContext<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<GroupContext>, Self::PublicError> {
+        self.read_payload(group_id, DataType::Context)
+    }
+
+    /// Returns the interim transcript hash for the group with group id `group_id`.
+    fn interim_transcript_hash<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        InterimTranscriptHash: traits::InterimTranscriptHash<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<InterimTranscriptHash>, Self::PublicError> {
+        self.read_payload(group_id, DataType::InterimTranscriptHash)
+    }
+
+    /// Returns the confirmation tag for the group with group id `group_id`.
+    fn confirmation_tag<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ConfirmationTag: traits::ConfirmationTag<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<Option<ConfirmationTag>, Self::PublicError> {
+        self.read_payload(group_id, DataType::ConfirmationTag)
+    }
+
+    /// Deletes the tree from storage
+    fn delete_tree<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.delete_payload(group_id, DataType::TreeSync)
+    }
+
+    /// Deletes the confirmation tag from storage
+    fn delete_confirmation_tag<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.delete_payload(group_id, DataType::ConfirmationTag)
+    }
+
+    /// Deletes the group context for the group with given id
+    fn delete_context<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.delete_payload(group_id, DataType::Context)
+    }
+
+    /// Deletes the interim transcript hash for the group with given id
+    fn delete_interim_transcript_hash<GroupId: traits::GroupId<CURRENT_VERSION>>(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.delete_payload(group_id, DataType::InterimTranscriptHash)
+    }
+
+    /// Removes an individual proposal from the proposal queue of the group with the provided id
+    fn remove_proposal<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+        proposal_ref: &ProposalRef,
+    ) -> Result<(), Self::PublicError> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let proposal_ref_bytes = Self::encode(proposal_ref)?;
+        self.connection().execute(
+            "DELETE FROM proposals WHERE group_id = ?1 AND proposal_ref = ?2",
+            params![group_id_bytes, proposal_ref_bytes],
+        )?;
+        Ok(())
+    }
+
+    /// Clear the proposal queue for the group with the given id.
+    fn clear_proposal_queue<
+        GroupId: traits::GroupId<CURRENT_VERSION>,
+        ProposalRef: traits::ProposalRef<CURRENT_VERSION>,
+    >(
+        &self,
+        group_id: &GroupId,
+    ) -> Result<(), Self::PublicError> {
+        self.delete_group_rows("proposals", group_id)
+    }
+}
+
+impl<C: Codec> MlsAssistStorageProvider for MlsAssistSqliteStorage<C> {
+    /// Transactions are implemented with savepoints, so they can be nested.
+    /// Only one thread at a time runs transactions. Other threads wait for
+    /// the running transaction to end before they access the database, so
+    /// their writes never become part of it.
+    fn with_transaction<T, E>(
+        &self,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Result<T, E>, StorageError<Self>> {
+        let transaction = self.transaction_lock.enter();
+        {
+            let connection = self.connection();
+            if transaction.is_outermost() && !connection.is_autocommit() {
+                // A transaction was aborted by a panic.
+                connection.execute_batch("ROLLBACK")?;
+            }
+            connection.execute_batch("SAVEPOINT mls_assist_transaction")?;
+        }
+        let result = f();
+        let statement = if result.is_ok() {
+            "RELEASE mls_assist_transaction"
+        } else {
+            "ROLLBACK TO mls_assist_transaction; RELEASE mls_assist_transaction"
+        };
+        self.connection().execute_batch(statement)?;
+        Ok(result)
+    }
+
+    fn group_ids<GroupId: DeserializeOwned>(&self) -> Result<Vec<GroupId>, StorageError<Self>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT group_id FROM group_infos
+            UNION SELECT group_id FROM past_group_states
+            UNION SELECT group_id FROM accepted_messages",
+        )?;
+        let group_id_bytes = statement
+            .query_map([], |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        group_id_bytes
+            .iter()
+            .map(|group_id_bytes| Self::decode(group_id_bytes))
+            .collect()
+    }
+
+    fn write_past_group_state(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl Serialize,
+        past_group_state: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let epoch_bytes = Self::encode(epoch)?;
+        let past_group_state_bytes = Self::encode(past_group_state)?;
+        self.connection().execute(
+            "INSERT OR REPLACE INTO past_group_states (group_id, epoch, past_group_state)
+                VALUES (?1, ?2, ?3)",
+            params![group_id_bytes, epoch_bytes, past_group_state_bytes],
+        )?;
+        Ok(())
+    }
+
+    fn read_past_group_state<PastGroupState: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl Serialize,
+    ) -> Result<Option<PastGroupState>, StorageError<Self>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let epoch_bytes = Self::encode(epoch)?;
+        let past_group_state_bytes = self
+            .connection()
+            .query_row(
+                "SELECT past_group_state FROM past_group_states
+                    WHERE group_id = ?1 AND epoch = ?2",
+                params![group_id_bytes, epoch_bytes],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?;
+        past_group_state_bytes
+            .map(|past_group_state_bytes| Self::decode(&past_group_state_bytes))
+            .transpose()
+    }
+
+    fn delete_past_group_state(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        epoch: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let epoch_bytes = Self::encode(epoch)?;
+        self.connection().execute(
+            "DELETE FROM past_group_states WHERE group_id = ?1 AND epoch = ?2",
+            params![group_id_bytes, epoch_bytes],
+        )?;
+        Ok(())
+    }
+
+    fn past_group_state_epochs<Epoch: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Vec<Epoch>, StorageError<Self>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let connection = self.connection();
+        let mut statement =
+            connection.prepare("SELECT epoch FROM past_group_states WHERE group_id = ?1")?;
+        let epoch_bytes = statement
+            .query_map(params![group_id_bytes], |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        epoch_bytes
+            .iter()
+            .map(|epoch_bytes| Self::decode(epoch_bytes))
+            .collect()
+    }
+
+    fn write_past_group_state_chunk(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl Serialize,
+        chunk: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let chunk_hash_bytes = Self::encode(chunk_hash)?;
+        let chunk_bytes = Self::encode(chunk)?;
+        self.connection().execute(
+            "INSERT OR REPLACE INTO past_group_state_chunks (group_id, chunk_hash, chunk)
+                VALUES (?1, ?2, ?3)",
+            params![group_id_bytes, chunk_hash_bytes, chunk_bytes],
+        )?;
+        Ok(())
+    }
+
+    fn read_past_group_state_chunk<Chunk: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl Serialize,
+    ) -> Result<Option<Chunk>, StorageError<Self>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let chunk_hash_bytes = Self::encode(chunk_hash)?;
+        let chunk_bytes = self
+            .connection()
+            .query_row(
+                "SELECT chunk FROM past_group_state_chunks
+                    WHERE group_id = ?1 AND chunk_hash = ?2",
+                params![group_id_bytes, chunk_hash_bytes],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?;
+        chunk_bytes
+            .map(|chunk_bytes| Self::decode(&chunk_bytes))
+            .transpose()
+    }
+
+    fn delete_past_group_state_chunk(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        chunk_hash: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        let group_id_bytes = Self::encode(group_id)?;
+        let chunk_hash_bytes = Self::encode(chunk_hash)?;
+        self.connection().execute(
+            "DELETE FROM past_group_state_chunks WHERE group_id = ?1 AND chunk_hash = ?2",
+            params![group_id_bytes, chunk_hash_bytes],
+        )?;
+        Ok(())
+    }
+
+    fn delete_past_group_states(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), StorageError<Self>> {
+        self.delete_group_rows("past_group_states", group_id)?;
+        self.delete_group_rows("past_group_state_chunks", group_id)
+    }
+
+    fn write_accepted_messages(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        accepted_messages: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write_group_value(
+            "accepted_messages",
+            "accepted_messages",
+            group_id,
+            accepted_messages,
+        )
+    }
+
+    fn read_accepted_messages<AcceptedMessages: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Option<AcceptedMessages>, StorageError<Self>> {
+        self.read_group_value("accepted_messages", "accepted_messages", group_id)
+    }
+
+    fn delete_accepted_messages(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), StorageError<Self>> {
+        self.delete_group_rows("accepted_messages", group_id)
+    }
+
+    fn write_group_info(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+        group_info: &impl Serialize,
+    ) -> Result<(), StorageError<Self>> {
+        self.write_group_value("group_infos", "group_info", group_id, group_info)
+    }
+
+    fn read_group_info<GroupInfo: DeserializeOwned>(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<Option<GroupInfo>, StorageError<Self>> {
+        self.read_group_value("group_infos", "group_info", group_id)
+    }
+
+    fn delete_group_info(
+        &self,
+        group_id: &impl GroupId<CURRENT_VERSION>,
+    ) -> Result<(), StorageError<Self>> {
+        self.delete_group_rows("group_infos", group_id)
+    }
+}
+
+/// An [`MlsAssistProvider`] using [`RustCrypto`] and an
+/// [`MlsAssistSqliteStorage`].
+pub struct MlsAssistSqliteRustCrypto<C: Codec, K: Clock = SystemClock> {
+    crypto: RustCrypto,
+    storage: MlsAssistSqliteStorage<C>,
+    clock: K,
+}
+
+impl<C: Codec, K: Clock> MlsAssistSqliteRustCrypto<C, K> {
+    pub fn new(storage: MlsAssistSqliteStorage<C>, clock: K) -> Self {
+        Self {
+            crypto: RustCrypto::default(),
+            storage,
+            clock,
+        }
+    }
+}
+
+impl<C: Codec, K: Clock + Default> From<MlsAssistSqliteStorage<C>>
+    for MlsAssistSqliteRustCrypto<C, K>
+{
+    fn from(storage: MlsAssistSqliteStorage<C>) -> Self {
+        Self::new(storage, K::default())
+    }
+}
+
+impl<C: Codec, K: Clock> MlsAssistProvider for MlsAssistSqliteRustCrypto<C, K> {
+    type Crypto = RustCrypto;
+
+    type Rand = RustCrypto;
+
+    type Storage = MlsAssistSqliteStorage<C>;
+
+    type Clock = K;
+
+    fn storage(&self) -> &Self::Storage {
+        &self.storage
+    }
+
+    fn crypto(&self) -> &Self::Crypto {
+        &self.crypto
+    }
+
+    fn rand(&self) -> &Self::Rand {
+        &self.crypto
+    }
+
+    fn clock(&self) -> &Self::Clock {
+        &self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_version(connection: &Connection) -> usize {
+        connection
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    /// The SQL of all tables and indexes in the database, sorted by name.
+    fn schema(connection: &Connection) -> Vec<String> {
+        let mut statement = connection
+            .prepare("SELECT sql FROM sqlite_master WHERE sql IS NOT NULL ORDER BY name")
+            .unwrap();
+        statement
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn migrations_are_applied_in_order() {
+        let mut expected = Connection::open_in_memory().unwrap();
+        migrate(&mut expected).unwrap();
+        assert_eq!(user_version(&expected), MIGRATIONS.len());
+        let expected_schema = schema(&expected);
+
+        // Starting from every intermediate version ends up at the same
+        // schema as starting from an empty database.
+        for version in 0..=MIGRATIONS.len() {
+            let mut connection = Connection::open_in_memory().unwrap();
+            apply_migrations(&mut connection, &MIGRATIONS[..version]).unwrap();
+            assert_eq!(user_version(&connection), version);
+            migrate(&mut connection).unwrap();
+            assert_eq!(user_version(&connection), MIGRATIONS.len());
+            assert_eq!(schema(&connection), expected_schema);
+        }
+
+        // Migrating an up-to-date database is a no-op.
+        migrate(&mut expected).unwrap();
+        assert_eq!(user_version(&expected), MIGRATIONS.len());
+        assert_eq!(schema(&expected), expected_schema);
+    }
+
+    #[test]
+    fn failed_migrations_are_rolled_back() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        migrate(&mut connection).unwrap();
+        let schema_before = schema(&connection);
+
+        let mut migrations = MIGRATIONS.to_vec();
+        migrations.push("CREATE TABLE new_table (id INTEGER); NOT SQL;");
+        apply_migrations(&mut connection, &migrations).unwrap_err();
+
+        assert_eq!(user_version(&connection), MIGRATIONS.len());
+        assert_eq!(schema(&connection), schema_before);
+    }
+
+    #[test]
+    fn databases_with_a_newer_version_are_refused() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+
+        let error =
+            MlsAssistSqliteStorage::<crate::test_utils::JsonCodec>::from_connection(connection)
+                .err()
+                .unwrap();
+        assert!(matches!(
+            error,
+            SqliteOpenError::UnsupportedVersion { version, latest }
+                if version == MIGRATIONS.len() + 1 && latest == MIGRATIONS.len()
+        ));
+    }
+
+    #[test]
+    fn reopened_databases_keep_their_version() {
+        let path = std::env::temp_dir().join(format!(
+            "mls-assist-sqlite-migrations-{}.db",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let storage = MlsAssistSqliteStorage::<crate::test_utils::JsonCodec>::open(&path).unwrap();
+        let group_id = openmls::prelude::GroupId::from_slice(&[1]);
+        storage.write_group_info(&group_id, &"group info").unwrap();
+        drop(storage);
+
+        let storage = MlsAssistSqliteStorage::<crate::test_utils::JsonCodec>::open(&path).unwrap();
+        assert_eq!(user_version(&storage.connection()), MIGRATIONS.len());
+        assert_eq!(
+            storage
+                .read_group_info::<String>(&group_id)
+                .unwrap()
+                .as_deref(),
+            Some("group info")
+        );
+        drop(storage);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,420 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Tests that every storage provider has to pass.
+
+use std::{sync::Barrier, thread, time::Duration};
+
+use openmls::prelude::GroupId;
+use openmls_rust_crypto::RustCrypto;
+
+use crate::{
+    group::Group,
+    provider_traits::{MlsAssistProvider, MlsAssistStorageProvider, SystemClock},
+    test_utils::{JsonCodec, TestGroup},
+};
+
+fn group_id(id: u8) -> GroupId {
+    GroupId::from_slice(&[id])
+}
+
+fn group_info<S: MlsAssistStorageProvider>(storage: &S, group_id: &GroupId) -> Option<String> {
+    storage.read_group_info(group_id).unwrap()
+}
+
+/// A provider using the storage under test.
+struct TestProvider<'a, S> {
+    storage: &'a S,
+    crypto: RustCrypto,
+    clock: SystemClock,
+}
+
+impl<S: MlsAssistStorageProvider> MlsAssistProvider for TestProvider<'_, S> {
+    type Crypto = RustCrypto;
+
+    type Rand = RustCrypto;
+
+    type Storage = S;
+
+    type Clock = SystemClock;
+
+    fn storage(&self) -> &Self::Storage {
+        self.storage
+    }
+
+    fn crypto(&self) -> &Self::Crypto {
+        &self.crypto
+    }
+
+    fn rand(&self) -> &Self::Rand {
+        &self.crypto
+    }
+
+    fn clock(&self) -> &Self::Clock {
+        &self.clock
+    }
+}
+
+fn records_round_trip<S: MlsAssistStorageProvider>(storage: &S) {
+    let other_group_id = group_id(2);
+    let group_id = group_id(1);
+    assert!(storage.group_ids::<GroupId>().unwrap().is_empty());
+
+    storage.write_group_info(&group_id, &"group info").unwrap();
+    storage
+        .write_accepted_messages(&group_id, &"accepted messages")
+        .unwrap();
+    storage
+        .write_past_group_state(&group_id, &1u64, &"epoch 1")
+        .unwrap();
+    storage
+        .write_past_group_state(&group_id, &2u64, &"epoch 2")
+        .unwrap();
+    storage
+        .write_past_group_state_chunk(&group_id, &0u8, &"chunk")
+        .unwrap();
+    storage
+        .write_group_info(&other_group_id, &"other group info")
+        .unwrap();
+
+    let mut group_ids = storage.group_ids::<GroupId>().unwrap();
+    group_ids.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+    assert_eq!(group_ids, [group_id.clone(), other_group_id.clone()]);
+    assert_eq!(
+        group_info(storage, &group_id).as_deref(),
+        Some("group info")
+    );
+    assert_eq!(
+        storage
+            .read_accepted_messages::<String>(&group_id)
+            .unwrap()
+            .as_deref(),
+        Some("accepted messages")
+    );
+    assert_eq!(
+        storage
+            .read_past_group_state::<String>(&group_id, &2u64)
+            .unwrap()
+            .as_deref(),
+        Some("epoch 2")
+    );
+    let mut epochs = storage.past_group_state_epochs::<u64>(&group_id).unwrap();
+    epochs.sort();
+    assert_eq!(epochs, [1, 2]);
+    assert_eq!(
+        storage
+            .read_past_group_state_chunk::<String>(&group_id, &0u8)
+            .unwrap()
+            .as_deref(),
+        Some("chunk")
+    );
+
+    // Writing a record again replaces it.
+    storage
+        .write_group_info(&group_id, &"new group info")
+        .unwrap();
+    assert_eq!(
+        group_info(storage, &group_id).as_deref(),
+        Some("new group info")
+    );
+
+    storage.delete_past_group_state(&group_id, &1u64).unwrap();
+    assert_eq!(
+        storage.past_group_state_epochs::<u64>(&group_id).unwrap(),
+        [2]
+    );
+    storage
+        .delete_past_group_state_chunk(&group_id, &0u8)
+        .unwrap();
+    assert_eq!(
+        storage
+            .read_past_group_state_chunk::<String>(&group_id, &0u8)
+            .unwrap(),
+        None
+    );
+
+    storage
+        .write_past_group_state_chunk(&group_id, &0u8, &"chunk")
+        .unwrap();
+    storage.delete_past_group_states(&group_id).unwrap();
+    assert!(
+        storage
+            .past_group_state_epochs::<u64>(&group_id)
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        storage
+            .read_past_group_state_chunk::<String>(&group_id, &0u8)
+            .unwrap(),
+        None
+    );
+
+    storage.delete_group_info(&group_id).unwrap();
+    storage.delete_accepted_messages(&group_id).unwrap();
+    assert_eq!(group_info(storage, &group_id), None);
+    assert_eq!(
+        storage.read_accepted_messages::<String>(&group_id).unwrap(),
+        None
+    );
+    assert_eq!(
+        group_info(storage, &other_group_id).as_deref(),
+        Some("other group info")
+    );
+    assert_eq!(storage.group_ids::<GroupId>().unwrap(), [other_group_id]);
+}
+
+fn groups_round_trip<S: MlsAssistStorageProvider>(storage: &S) {
+    let provider = TestProvider {
+        storage,
+        crypto: RustCrypto::default(),
+        clock: SystemClock,
+    };
+    let test_group = TestGroup::new();
+    let group = test_group.assisted_group(&provider);
+    let group_id = group.group_info().group_context().group_id().clone();
+
+    let loaded_group = Group::load(storage, &group_id).unwrap().unwrap();
+    assert_eq!(loaded_group.epoch(), group.epoch());
+    assert_eq!(
+        loaded_group.export_ratchet_tree(),
+        group.export_ratchet_tree()
+    );
+    assert_eq!(
+        storage.group_ids::<GroupId>().unwrap(),
+        std::slice::from_ref(&group_id)
+    );
+
+    Group::delete(storage, &group_id).unwrap();
+    assert!(Group::load(storage, &group_id).unwrap().is_none());
+    assert!(storage.group_ids::<GroupId>().unwrap().is_empty());
+}
+
+fn failed_transactions_are_rolled_back<S: MlsAssistStorageProvider>(storage: &S) {
+    let group_id = group_id(1);
+    storage.write_group_info(&group_id, &"before").unwrap();
+    storage
+        .write_past_group_state_chunk(&group_id, &0u8, &"chunk")
+        .unwrap();
+
+    let result = storage
+        .with_transaction(|| {
+            storage.write_group_info(&group_id, &"during").unwrap();
+            storage
+                .write_accepted_messages(&group_id, &"during")
+                .unwrap();
+            storage
+                .write_past_group_state(&group_id, &1u64, &"during")
+                .unwrap();
+            storage.delete_past_group_states(&group_id).unwrap();
+            assert_eq!(group_info(storage, &group_id).as_deref(), Some("during"));
+            Err::<(), _>("failed")
+        })
+        .unwrap();
+
+    assert_eq!(result, Err("failed"));
+    assert_eq!(group_info(storage, &group_id).as_deref(), Some("before"));
+    assert_eq!(
+        storage.read_accepted_messages::<String>(&group_id).unwrap(),
+        None
+    );
+    assert!(
+        storage
+            .past_group_state_epochs::<u64>(&group_id)
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        storage
+            .read_past_group_state_chunk::<String>(&group_id, &0u8)
+            .unwrap()
+            .as_deref(),
+        Some("chunk")
+    );
+
+    storage
+        .with_transaction(|| {
+            storage.write_group_info(&group_id, &"after").unwrap();
+            Ok::<_, ()>(())
+        })
+        .unwrap()
+        .unwrap();
+    assert_eq!(group_info(storage, &group_id).as_deref(), Some("after"));
+}
+
+fn nested_transactions_are_rolled_back_separately<S: MlsAssistStorageProvider>(storage: &S) {
+    let outer_group_id = group_id(1);
+    let inner_group_id = group_id(2);
+    storage
+        .with_transaction(|| {
+            storage.write_group_info(&outer_group_id, &"outer").unwrap();
+            let inner_result = storage
+                .with_transaction(|| {
+                    storage.write_group_info(&inner_group_id, &"inner").unwrap();
+                    storage.write_group_info(&outer_group_id, &"inner").unwrap();
+                    Err::<(), _>(())
+                })
+                .unwrap();
+            assert!(inner_result.is_err());
+            Ok::<_, ()>(())
+        })
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        group_info(storage, &outer_group_id).as_deref(),
+        Some("outer")
+    );
+    assert_eq!(group_info(storage, &inner_group_id), None);
+}
+
+/// Writes made by another thread while a transaction runs survive the
+/// transaction being rolled back.
+fn rollbacks_keep_writes_of_other_threads<S: MlsAssistStorageProvider + Sync>(storage: &S) {
+    let transaction_group_id = group_id(1);
+    let other_group_id = group_id(2);
+    let transaction_started = Barrier::new(2);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            storage
+                .with_transaction(|| {
+                    storage
+                        .write_group_info(&transaction_group_id, &"transaction")
+                        .unwrap();
+                    transaction_started.wait();
+                    // Give the other thread the chance to write while the
+                    // transaction is running.
+                    thread::sleep(Duration::from_millis(50));
+                    Err::<(), _>(())
+                })
+                .unwrap()
+                .unwrap_err();
+        });
+        transaction_started.wait();
+        storage.write_group_info(&other_group_id, &"other").unwrap();
+    });
+
+    assert_eq!(group_info(storage, &transaction_group_id), None);
+    assert_eq!(
+        group_info(storage, &other_group_id).as_deref(),
+        Some("other")
+    );
+}
+
+/// Transactions running on different threads at the same time neither see
+/// nor roll back each other's writes.
+fn concurrent_transactions_are_isolated<S: MlsAssistStorageProvider + Sync>(storage: &S) {
+    const ROUNDS: u8 = 20;
+    let committing_group_id = group_id(1);
+    let failing_group_id = group_id(2);
+    let start = Barrier::new(2);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            start.wait();
+            for round in 0..ROUNDS {
+                storage
+                    .with_transaction(|| {
+                        storage
+                            .write_past_group_state(&committing_group_id, &round, &"committed")
+                            .unwrap();
+                        assert_eq!(group_info(storage, &failing_group_id), None);
+                        Ok::<_, ()>(())
+                    })
+                    .unwrap()
+                    .unwrap();
+            }
+        });
+        start.wait();
+        for round in 0..ROUNDS {
+            storage
+                .with_transaction(|| {
+                    storage
+                        .write_group_info(&failing_group_id, &"failed")
+                        .unwrap();
+                    storage
+                        .write_past_group_state(&failing_group_id, &round, &"failed")
+                        .unwrap();
+                    thread::yield_now();
+                    Err::<(), _>(())
+                })
+                .unwrap()
+                .unwrap_err();
+        }
+    });
+
+    let mut epochs = storage
+        .past_group_state_epochs::<u8>(&committing_group_id)
+        .unwrap();
+    epochs.sort();
+    assert_eq!(epochs, (0..ROUNDS).collect::<Vec<_>>());
+    assert!(
+        storage
+            .past_group_state_epochs::<u8>(&failing_group_id)
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(group_info(storage, &failing_group_id), None);
+}
+
+/// Instantiates the tests for the storage provider created by `$storage`.
+macro_rules! storage_tests {
+    ($name:ident, $storage:expr) => {
+        mod $name {
+            use super::*;
+
+            #[test]
+            fn records_round_trip() {
+                super::records_round_trip(&$storage);
+            }
+
+            #[test]
+            fn groups_round_trip() {
+                super::groups_round_trip(&$storage);
+            }
+
+            #[test]
+            fn failed_transactions_are_rolled_back() {
+                super::failed_transactions_are_rolled_back(&$storage);
+            }
+
+            #[test]
+            fn nested_transactions_are_rolled_back_separately() {
+                super::nested_transactions_are_rolled_back_separately(&$storage);
+            }
+
+            #[test]
+            fn rollbacks_keep_writes_of_other_threads() {
+                super::rollbacks_keep_writes_of_other_threads(&$storage);
+            }
+
+            #[test]
+            fn concurrent_transactions_are_isolated() {
+                super::concurrent_transactions_are_isolated(&$storage);
+            }
+        }
+    };
+}
+
+storage_tests!(
+    memory,
+    crate::memory_provider::MlsAssistMemoryStorage::<JsonCodec>::default()
+);
+#[cfg(feature = "sqlite")]
+storage_tests!(
+    sqlite,
+    crate::sqlite_provider::MlsAssistSqliteStorage::<JsonCodec>::open_in_memory().unwrap()
+);
+storage_tests!(
+    kv,
+    crate::kv_provider::KvStorage::<crate::test_utils::MemoryStore, JsonCodec>::new(
+        Default::default()
+    )
+);
+#[cfg(feature = "redb")]
+storage_tests!(
+    redb,
+    crate::redb_provider::MlsAssistRedbStorage::<JsonCodec>::new(
+        crate::redb_provider::RedbStore::open_in_memory().unwrap()
+    )
+);
@@ -0,0 +1,649 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Helpers to drive a group through a real MLS client in tests.
+
+#[cfg(feature = "async")]
+use std::task::{Context, Poll, Waker};
+use std::{
+    collections::BTreeMap,
+    io,
+    sync::{
+        RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use openmls::{
+    group::{CommitBuilder, Initial},
+    prelude::{
+        BasicCredential, Capabilities, Ciphersuite, Credential, CredentialWithKey, ExtensionType,
+        Extensions, JoinProposal, KeyPackage, LeafNodeIndex, LeafNodeParameters, MlsGroup,
+        MlsGroupCreateConfig, MlsGroupJoinConfig, MlsMessageBodyIn, MlsMessageIn, MlsMessageOut,
+        NewSignerBundle, PURE_PLAINTEXT_WIRE_FORMAT_POLICY, ProcessedMessageContent, RatchetTreeIn,
+        SignaturePublicKey, StagedWelcome, group_info::VerifiableGroupInfo,
+    },
+};
+use openmls_basic_credential::SignatureKeyPair;
+use openmls_rust_crypto::OpenMlsRustCrypto;
+use openmls_traits::{OpenMlsProvider, signatures::Signer as _};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    group::{Group, admin_roles::ADMIN_ROLES_EXTENSION_TYPE},
+    kv_provider::{KeyValueStore, KvEntries, KvOperation},
+    memory_provider::Codec,
+    messages::{AssistedMessageIn, AssistedMessageOut, AssistedWelcome},
+    provider_traits::MlsAssistProvider,
+    tls_codec::{DeserializeBytes as _, Serialize as _, VLBytes},
+};
+
+pub(crate) const CIPHERSUITE: Ciphersuite =
+    Ciphersuite::MLS_128_DHKEMX25519_AES128GCM_SHA256_Ed25519;
+
+#[derive(Default)]
+pub(crate) struct JsonCodec;
+
+impl Codec for JsonCodec {
+    type Error = serde_json::Error;
+
+    fn to_vec<T: Serialize>(payload: &T) -> Result<Vec<u8>, Self::Error> {
+        serde_json::to_vec(payload)
+    }
+
+    fn from_slice<T: DeserializeOwned>(data: &[u8]) -> Result<T, Self::Error> {
+        serde_json::from_slice(data)
+    }
+}
+
+/// A [`KeyValueStore`] in memory whose batches can be made to fail.
+#[derive(Default)]
+pub(crate) struct MemoryStore {
+    entries: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
+    fail_batches: AtomicBool,
+}
+
+impl MemoryStore {
+    pub(crate) fn fail_batches(&self, fail_batches: bool) {
+        self.fail_batches.store(fail_batches, Ordering::SeqCst);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+}
+
+impl KeyValueStore for MemoryStore {
+    type Error = io::Error;
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self.entries.read().unwrap().get(key).cloned())
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), Self::Error> {
+        self.entries
+            .write()
+            .unwrap()
+            .insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<(), Self::Error> {
+        self.entries.write().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Result<KvEntries, Self::Error> {
+        Ok(self
+            .entries
+            .read()
+            .unwrap()
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn scan_prefix_keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>, Self::Error> {
+        Ok(self
+            .entries
+            .read()
+            .unwrap()
+            .range(prefix.to_vec()..)
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+
+    fn batch(&self, operations: Vec<KvOperation>) -> Result<(), Self::Error> {
+        if self.fail_batches.load(Ordering::SeqCst) {
+            return Err(io::Error::other("batch failed"));
+        }
+        let mut entries = self.entries.write().unwrap();
+        for operation in operations {
+            match operation {
+                KvOperation::Put { key, value } => entries.insert(key, value),
+                KvOperation::Delete { key } => entries.remove(&key),
+            };
+        }
+        Ok(())
+    }
+}
+
+/// An MLS client with a single signature key.
+pub(crate) struct Client {
+    pub(crate) name: String,
+    pub(crate) provider: OpenMlsRustCrypto,
+    pub(crate) signer: SignatureKeyPair,
+    credential_with_key: CredentialWithKey,
+}
+
+impl Client {
+    pub(crate) fn new(identity: &str) -> Self {
+        let signer = SignatureKeyPair::new(CIPHERSUITE.signature_algorithm()).unwrap();
+        let credential_with_key = CredentialWithKey {
+            credential: BasicCredential::new(identity.as_bytes().to_vec()).into(),
+            signature_key: signer.public().into(),
+        };
+        Self {
+            name: identity.to_owned(),
+            provider: OpenMlsRustCrypto::default(),
+            signer,
+            credential_with_key,
+        }
+    }
+
+    pub(crate) fn signature_key(&self) -> SignaturePublicKey {
+        self.credential_with_key.signature_key.clone()
+    }
+
+    pub(crate) fn credential(&self) -> &Credential {
+        &self.credential_with_key.credential
+    }
+
+    pub(crate) fn key_package(&self) -> KeyPackage {
+        KeyPackage::builder()
+            .leaf_node_capabilities(capabilities())
+            .build(
+                CIPHERSUITE,
+                &self.provider,
+                &self.signer,
+                self.credential_with_key.clone(),
+            )
+            .unwrap()
+            .key_package()
+            .clone()
+    }
+
+    /// Returns a proposal by this client to join the group, as sent by a
+    /// [`Sender::NewMemberProposal`](openmls::prelude::Sender).
+    pub(crate) fn join_proposal(&self, mls_group: &MlsGroup) -> MlsMessageOut {
+        JoinProposal::new::<<OpenMlsRustCrypto as OpenMlsProvider>::StorageProvider>(
+            self.key_package(),
+            mls_group.group_id().clone(),
+            mls_group.epoch(),
+            &self.signer,
+        )
+        .unwrap()
+    }
+}
+
+/// Capabilities of all test clients. Besides the defaults, they support the
+/// admin roles extension.
+fn capabilities() -> Capabilities {
+    Capabilities::builder()
+        .extensions(vec![ExtensionType::Unknown(ADMIN_ROLES_EXTENSION_TYPE)])
+        .build()
+}
+
+/// A client together with its view of the group.
+pub(crate) struct TestMember {
+    pub(crate) client: Client,
+    pub(crate) mls_group: MlsGroup,
+}
+
+/// A group of clients whose commits and proposals are distributed to the
+/// other members and can be sent through an assisted [`Group`]. The first
+/// member is the creator of the group.
+pub(crate) struct TestGroup {
+    members: Vec<TestMember>,
+}
+
+impl TestGroup {
+    pub(crate) fn new() -> Self {
+        Self::with_group_context_extensions(Extensions::empty())
+    }
+
+    /// Create a group whose initial group context contains the given
+    /// extensions.
+    pub(crate) fn with_group_context_extensions(extensions: Extensions) -> Self {
+        let creator = Client::new("creator");
+        let mls_group = MlsGroup::new(
+            &creator.provider,
+            &creator.signer,
+            &MlsGroupCreateConfig::builder()
+                .ciphersuite(CIPHERSUITE)
+                .wire_format_policy(PURE_PLAINTEXT_WIRE_FORMAT_POLICY)
+                .capabilities(capabilities())
+                .with_group_context_extensions(extensions)
+                .unwrap()
+                .build(),
+            creator.credential_with_key.clone(),
+        )
+        .unwrap();
+        Self {
+            members: vec![TestMember {
+                client: creator,
+                mls_group,
+            }],
+        }
+    }
+
+    /// Returns the member with the given name.
+    pub(crate) fn member(&self, name: &str) -> &TestMember {
+        self.members
+            .iter()
+            .find(|member| member.client.name == name)
+            .unwrap()
+    }
+
+    pub(crate) fn member_mut(&mut self, name: &str) -> &mut TestMember {
+        self.members
+            .iter_mut()
+            .find(|member| member.client.name == name)
+            .unwrap()
+    }
+
+    /// Returns the leaf index of the member with the given name.
+    pub(crate) fn leaf_index(&self, name: &str) -> LeafNodeIndex {
+        self.member(name).mls_group.own_leaf_index()
+    }
+
+    /// Returns the creator's current group info and ratchet tree, from which
+    /// the assisted group is created.
+    pub(crate) fn group_info_and_tree(&self) -> (VerifiableGroupInfo, RatchetTreeIn) {
+        let creator = &self.members[0];
+        let group_info = creator.export_group_info();
+        let MlsMessageBodyIn::GroupInfo(verifiable_group_info) = message_body(group_info) else {
+            panic!("Expected a GroupInfo.");
+        };
+        let ratchet_tree = RatchetTreeIn::from(creator.mls_group.export_ratchet_tree());
+        (verifiable_group_info, ratchet_tree)
+    }
+
+    /// Create the assisted group from the creator's current state.
+    pub(crate) fn assisted_group(&self, provider: &impl MlsAssistProvider) -> Group {
+        let (verifiable_group_info, ratchet_tree) = self.group_info_and_tree();
+        Group::new(provider, verifiable_group_info, ratchet_tree).unwrap()
+    }
+
+    /// Let the creator commit the addition of the given key packages. Returns
+    /// the commit as an assisted message and the Welcome.
+    pub(crate) fn add_members(
+        &mut self,
+        key_packages: &[KeyPackage],
+    ) -> (AssistedMessageIn, MlsMessageOut) {
+        let key_packages = key_packages.to_vec();
+        let creator = self.members[0].client.name.clone();
+        let (commit, welcome) = self.commit(&creator, |builder| builder.propose_adds(key_packages));
+        (commit, welcome.unwrap())
+    }
+
+    /// Let the given member commit all pending proposals and the proposals
+    /// added by `propose`. The commit is merged by all members. Returns the
+    /// commit as an assisted message and the Welcome, if there is one.
+    pub(crate) fn commit(
+        &mut self,
+        committer: &str,
+        propose: impl for<'a> FnOnce(CommitBuilder<'a, Initial>) -> CommitBuilder<'a, Initial>,
+    ) -> (AssistedMessageIn, Option<MlsMessageOut>) {
+        let member = self.member_mut(committer);
+        let provider = &member.client.provider;
+        let bundle = propose(member.mls_group.commit_builder())
+            .load_psks(provider.storage())
+            .unwrap()
+            .build(
+                provider.rand(),
+                provider.crypto(),
+                &member.client.signer,
+                |_| true,
+            )
+            .unwrap()
+            .stage_commit(provider)
+            .unwrap();
+        let (commit, welcome, _) = bundle.into_messages();
+        (self.merge_commit(committer, commit), welcome)
+    }
+
+    /// Let the given member commit an update of its leaf with a new
+    /// signature key. Returns the commit as an assisted message.
+    pub(crate) fn rotate_signature_key(&mut self, committer: &str) -> AssistedMessageIn {
+        let member = self.member_mut(committer);
+        let client = &mut member.client;
+        let signer = SignatureKeyPair::new(CIPHERSUITE.signature_algorithm()).unwrap();
+        let credential_with_key = CredentialWithKey {
+            credential: client.credential_with_key.credential.clone(),
+            signature_key: signer.public().into(),
+        };
+        let bundle = member
+            .mls_group
+            .self_update_with_new_signer(
+                &client.provider,
+                &client.signer,
+                NewSignerBundle {
+                    signer: &signer,
+                    credential_with_key: credential_with_key.clone(),
+                },
+                LeafNodeParameters::default(),
+            )
+            .unwrap();
+        client.signer = signer;
+        client.credential_with_key = credential_with_key;
+        self.merge_commit(committer, bundle.into_commit())
+    }
+
+    /// Like [`Self::commit`], but the commit is discarded instead of merged,
+    /// as if it had been rejected or another commit for the same epoch had
+    /// won.
+    pub(crate) fn unmerged_commit(
+        &mut self,
+        committer: &str,
+        propose: impl for<'a> FnOnce(CommitBuilder<'a, Initial>) -> CommitBuilder<'a, Initial>,
+    ) -> AssistedMessageIn {
+        let member = self.member_mut(committer);
+        let provider = &member.client.provider;
+        let bundle = propose(member.mls_group.commit_builder())
+            .create_group_info(true)
+            .load_psks(provider.storage())
+            .unwrap()
+            .build(
+                provider.rand(),
+                provider.crypto(),
+                &member.client.signer,
+                |_| true,
+            )
+            .unwrap()
+            .stage_commit(provider)
+            .unwrap();
+        member
+            .mls_group
+            .clear_pending_commit(provider.storage())
+            .unwrap();
+        let (commit, _, group_info) = bundle.into_messages();
+        assisted(commit, group_info)
+    }
+
+    /// Let the given member propose to remove another member. The proposal
+    /// is stored by all members. Returns the proposal as an assisted message.
+    pub(crate) fn propose_remove(&mut self, proposer: &str, removed: &str) -> AssistedMessageIn {
+        let removed = self.leaf_index(removed);
+        let member = self.member_mut(proposer);
+        let (proposal, _) = member
+            .mls_group
+            .propose_remove_member(&member.client.provider, &member.client.signer, removed)
+            .unwrap();
+        self.distribute(proposer, proposal.clone());
+        assisted(proposal, None)
+    }
+
+    /// Let the given member propose an update of its own leaf. The proposal
+    /// is stored by all members. Returns the proposal as an assisted message.
+    pub(crate) fn propose_self_update(&mut self, proposer: &str) -> AssistedMessageIn {
+        let member = self.member_mut(proposer);
+        let (proposal, _) = member
+            .mls_group
+            .propose_self_update(
+                &member.client.provider,
+                &member.client.signer,
+                LeafNodeParameters::default(),
+            )
+            .unwrap();
+        self.distribute(proposer, proposal.clone());
+        assisted(proposal, None)
+    }
+
+    /// Let the given member send an application message, which is always
+    /// encrypted. Returns the message as an assisted message.
+    pub(crate) fn application_message(&mut self, sender: &str) -> AssistedMessageIn {
+        let member = self.member_mut(sender);
+        let message = member
+            .mls_group
+            .create_message(&member.client.provider, &member.client.signer, b"hello")
+            .unwrap();
+        assisted(message, None)
+    }
+
+    /// Let the given client ask to join the group with a proposal, which is
+    /// stored by all members. Returns the proposal as an assisted message.
+    pub(crate) fn propose_join(&mut self, joiner: &Client) -> AssistedMessageIn {
+        let proposal = joiner.join_proposal(&self.members[0].mls_group);
+        self.distribute(&joiner.name, proposal.clone());
+        assisted(proposal, None)
+    }
+
+    /// Let the given client join the group with the given Welcome.
+    pub(crate) fn join(&mut self, joiner: Client, welcome: MlsMessageOut) {
+        let MlsMessageBodyIn::Welcome(welcome) = message_body(welcome) else {
+            panic!("Expected a Welcome.");
+        };
+        let ratchet_tree = self.members[0].mls_group.export_ratchet_tree();
+        let mls_group = StagedWelcome::new_from_welcome(
+            &joiner.provider,
+            &join_config(),
+            welcome,
+            Some(ratchet_tree.into()),
+        )
+        .unwrap()
+        .into_group(&joiner.provider)
+        .unwrap();
+        self.members.push(TestMember {
+            client: joiner,
+            mls_group,
+        });
+    }
+
+    /// Let the given client join the group with an external commit. Returns
+    /// the commit as an assisted message.
+    pub(crate) fn join_by_external_commit(&mut self, joiner: Client) -> AssistedMessageIn {
+        let (verifiable_group_info, ratchet_tree) = self.group_info_and_tree();
+        let (mls_group, commit, _) = MlsGroup::join_by_external_commit(
+            &joiner.provider,
+            &joiner.signer,
+            Some(ratchet_tree),
+            verifiable_group_info,
+            &join_config(),
+            Some(capabilities()),
+            None,
+            &[],
+            joiner.credential_with_key.clone(),
+        )
+        .unwrap();
+        let name = joiner.name.clone();
+        self.members.push(TestMember {
+            client: joiner,
+            mls_group,
+        });
+        self.merge_commit(&name, commit)
+    }
+
+    /// Merge the pending commit of the given member, distribute the commit to
+    /// the other members and return it as an assisted message.
+    fn merge_commit(&mut self, committer: &str, commit: MlsMessageOut) -> AssistedMessageIn {
+        let member = self.member_mut(committer);
+        member
+            .mls_group
+            .merge_pending_commit(&member.client.provider)
+            .unwrap();
+        let group_info = member.export_group_info();
+        self.distribute(committer, commit.clone());
+        self.members.retain(|member| member.mls_group.is_active());
+        assisted(commit, Some(group_info))
+    }
+
+    /// Let all members but the sender process the given commit or proposal.
+    pub(crate) fn distribute(&mut self, sender: &str, message: MlsMessageOut) {
+        for member in self
+            .members
+            .iter_mut()
+            .filter(|member| member.client.name != sender)
+        {
+            let MlsMessageBodyIn::PublicMessage(public_message) = message_body(message.clone())
+            else {
+                panic!("Expected a PublicMessage.");
+            };
+            let provider = &member.client.provider;
+            let processed_message = member
+                .mls_group
+                .process_message(provider, public_message)
+                .unwrap();
+            match processed_message.into_content() {
+                ProcessedMessageContent::StagedCommitMessage(staged_commit) => member
+                    .mls_group
+                    .merge_staged_commit(provider, *staged_commit)
+                    .unwrap(),
+                ProcessedMessageContent::ProposalMessage(proposal)
+                | ProcessedMessageContent::ExternalJoinProposalMessage(proposal) => member
+                    .mls_group
+                    .store_pending_proposal(provider.storage(), *proposal)
+                    .unwrap(),
+                _ => panic!("Expected a commit or proposal."),
+            }
+        }
+    }
+}
+
+impl TestMember {
+    fn export_group_info(&self) -> MlsMessageOut {
+        self.mls_group
+            .export_group_info(self.client.provider.crypto(), &self.client.signer, false)
+            .unwrap()
+    }
+}
+
+fn join_config() -> MlsGroupJoinConfig {
+    MlsGroupJoinConfig::builder()
+        .wire_format_policy(PURE_PLAINTEXT_WIRE_FORMAT_POLICY)
+        .build()
+}
+
+/// Returns the given message and group info as an assisted message, as
+/// received by the assisting server.
+pub(crate) fn assisted(
+    mls_message: MlsMessageOut,
+    group_info: Option<MlsMessageOut>,
+) -> AssistedMessageIn {
+    let assisted_message = AssistedMessageOut::new(mls_message, group_info)
+        .unwrap()
+        .tls_serialize_detached()
+        .unwrap();
+    AssistedMessageIn::tls_deserialize_exact_bytes(&assisted_message).unwrap()
+}
+
+/// Returns the given Welcome as an assisted Welcome, as received by the
+/// assisting server.
+pub(crate) fn assisted_welcome(welcome: MlsMessageOut) -> AssistedWelcome {
+    let welcome = welcome.tls_serialize_detached().unwrap();
+    AssistedWelcome::tls_deserialize_exact_bytes(&welcome).unwrap()
+}
+
+/// Returns a proposal by a [`Sender::NewMemberProposal`] to remove the
+/// member at the given leaf, signed by `client`. Such a proposal is invalid,
+/// so openmls can't create it.
+///
+/// [`Sender::NewMemberProposal`]: openmls::prelude::Sender::NewMemberProposal
+pub(crate) fn new_member_remove_proposal(
+    client: &Client,
+    mls_group: &MlsGroup,
+    removed: LeafNodeIndex,
+) -> AssistedMessageIn {
+    // Sender type new_member_proposal.
+    let sender = [3];
+    // Proposal type remove.
+    let proposal = [[0, 3].as_slice(), &removed.u32().to_be_bytes()].concat();
+    encode_proposal(client, mls_group, &sender, &proposal)
+}
+
+/// Returns a ReInit proposal by the external sender with the given index,
+/// signed by `client`. External senders may not send ReInit proposals, so
+/// openmls can't create it.
+pub(crate) fn external_reinit_proposal(
+    client: &Client,
+    mls_group: &MlsGroup,
+    sender_index: u32,
+) -> AssistedMessageIn {
+    // Sender type external.
+    let sender = [[2].as_slice(), &sender_index.to_be_bytes()].concat();
+    // Proposal type reinit, followed by the group ID, version mls10, the
+    // ciphersuite and no extensions.
+    let mut proposal = vec![0, 5];
+    proposal.extend(
+        VLBytes::new(mls_group.group_id().as_slice().to_vec())
+            .tls_serialize_detached()
+            .unwrap(),
+    );
+    proposal.extend([0, 1]);
+    proposal.extend(u16::from(CIPHERSUITE).to_be_bytes());
+    proposal.push(0);
+    encode_proposal(client, mls_group, &sender, &proposal)
+}
+
+/// Encode a proposal by a non-member in the current epoch of the given group
+/// as a [`PublicMessage`](openmls::prelude::PublicMessage) signed by
+/// `client`, and return it as an assisted message.
+fn encode_proposal(
+    client: &Client,
+    mls_group: &MlsGroup,
+    sender: &[u8],
+    proposal: &[u8],
+) -> AssistedMessageIn {
+    // Version mls10 and wire format public_message.
+    let header = [0u8, 1, 0, 1];
+    let mut framed_content = VLBytes::new(mls_group.group_id().as_slice().to_vec())
+        .tls_serialize_detached()
+        .unwrap();
+    framed_content.extend(mls_group.epoch().as_u64().to_be_bytes());
+    framed_content.extend(sender);
+    // Empty authenticated data and content type proposal.
+    framed_content.extend([0, 2]);
+    framed_content.extend(proposal);
+
+    // Non-members don't sign the group context.
+    let framed_content_tbs = [header.as_slice(), &framed_content].concat();
+    let sign_content = [
+        VLBytes::new(b"MLS 1.0 FramedContentTBS".to_vec()),
+        VLBytes::new(framed_content_tbs),
+    ]
+    .iter()
+    .flat_map(|bytes| bytes.tls_serialize_detached().unwrap())
+    .collect::<Vec<_>>();
+    let signature = client.signer.sign(&sign_content).unwrap();
+
+    let mut assisted_message = [header.as_slice(), &framed_content].concat();
+    assisted_message.extend(VLBytes::new(signature).tls_serialize_detached().unwrap());
+    // No group info.
+    assisted_message.push(0);
+    AssistedMessageIn::tls_deserialize_exact_bytes(&assisted_message).unwrap()
+}
+
+/// Returns the body of the given message as received.
+pub(crate) fn message_body(mls_message: MlsMessageOut) -> MlsMessageBodyIn {
+    let serialized = mls_message.tls_serialize_detached().unwrap();
+    MlsMessageIn::tls_deserialize_exact_bytes(&serialized)
+        .unwrap()
+        .extract()
+}
+
+/// Run the given future to completion on the current thread.
+#[cfg(feature = "async")]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+        std::thread::yield_now();
+    }
+}
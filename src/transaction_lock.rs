@@ -0,0 +1,123 @@
+// SPDX-FileCopyrightText: 2025 Phoenix R&D GmbH <hello@phnx.im>
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! A lock that lets one thread at a time run transactions on a storage
+//! provider.
+
+use std::{
+    sync::{Condvar, Mutex},
+    thread::{self, ThreadId},
+};
+
+/// A lock held by one thread at a time. The thread holding it can enter it
+/// again, so that transactions can be nested.
+#[derive(Default)]
+pub(crate) struct TransactionLock {
+    /// The thread holding the lock and how often it has entered it.
+    owner: Mutex<Option<(ThreadId, usize)>>,
+    released: Condvar,
+}
+
+impl TransactionLock {
+    /// Enter the lock, waiting until no other thread holds it. The lock is
+    /// held until the returned guard and all guards entered after it on the
+    /// same thread are dropped.
+    pub(crate) fn enter(&self) -> TransactionGuard<'_> {
+        let current_thread = thread::current().id();
+        let mut owner = self
+            .released
+            .wait_while(self.owner.lock().unwrap(), |owner| {
+                owner.is_some_and(|(thread, _)| thread != current_thread)
+            })
+            .unwrap();
+        let (_, depth) = owner.get_or_insert((current_thread, 0));
+        *depth += 1;
+        let depth = *depth;
+        TransactionGuard { lock: self, depth }
+    }
+
+    /// Returns whether the current thread holds the lock.
+    pub(crate) fn is_held_by_current_thread(&self) -> bool {
+        self.owner
+            .lock()
+            .unwrap()
+            .is_some_and(|(thread, _)| thread == thread::current().id())
+    }
+}
+
+/// Keeps a [`TransactionLock`] entered until it is dropped.
+pub(crate) struct TransactionGuard<'a> {
+    lock: &'a TransactionLock,
+    depth: usize,
+}
+
+impl TransactionGuard<'_> {
+    /// Returns whether the lock wasn't held by the current thread before this
+    /// guard entered it.
+    pub(crate) fn is_outermost(&self) -> bool {
+        self.depth == 1
+    }
+}
+
+impl Drop for TransactionGuard<'_> {
+    fn drop(&mut self) {
+        let mut owner = self.lock.owner.lock().unwrap();
+        let Some((_, depth)) = owner.as_mut() else {
+            return;
+        };
+        *depth -= 1;
+        if *depth == 0 {
+            *owner = None;
+            drop(owner);
+            self.lock.released.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    fn lock_is_reentrant() {
+        let lock = TransactionLock::default();
+        let outer = lock.enter();
+        let inner = lock.enter();
+        assert!(outer.is_outermost());
+        assert!(!inner.is_outermost());
+        drop(inner);
+        assert!(lock.is_held_by_current_thread());
+        drop(outer);
+        assert!(!lock.is_held_by_current_thread());
+    }
+
+    #[test]
+    fn other_threads_wait_for_the_lock() {
+        let lock = Arc::new(TransactionLock::default());
+        let entered = Arc::new(AtomicBool::new(false));
+        let guard = lock.enter();
+        let other_thread = {
+            let lock = lock.clone();
+            let entered = entered.clone();
+            thread::spawn(move || {
+                let guard = lock.enter();
+                assert!(guard.is_outermost());
+                entered.store(true, Ordering::SeqCst);
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!entered.load(Ordering::SeqCst));
+        drop(guard);
+        other_thread.join().unwrap();
+        assert!(entered.load(Ordering::SeqCst));
+    }
+}